@@ -0,0 +1,235 @@
+use crate::settings::Settings;
+use crate::subject::{NewHwOptions, Subject};
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+
+/// A tool for managing homework (HW) directories.
+///
+/// When no subcommand is given, the interface configured in the settings file is started.
+#[derive(Parser)]
+#[command(version)]
+pub struct Cli {
+    /// Only print what the command would do (create, open, export or package a HW),
+    /// without touching the filesystem or running anything.
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// List all subjects.
+    List {
+        /// Print a JSON description of every subject and its HW directories.
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Create a new HW directory in a subject.
+    New {
+        /// Name of the subject, or its index in the subjects list.
+        subject: String,
+
+        /// Use this file as the questions file, instead of the most recent download.
+        #[arg(long)]
+        questions_file: Option<PathBuf>,
+
+        /// Use this file as an attachment, instead of selecting attachments automatically.
+        /// Can be given multiple times.
+        #[arg(long = "attachment")]
+        attachments: Vec<PathBuf>,
+    },
+
+    /// Open a HW directory in a subject.
+    Open {
+        /// Name of the subject, or its index in the subjects list.
+        subject: String,
+
+        /// The HW number. Defaults to the last HW.
+        num: Option<usize>,
+    },
+
+    /// Print the path of a HW directory in a subject.
+    Path {
+        /// Name of the subject, or its index in the subjects list.
+        subject: String,
+
+        /// The HW number. Defaults to the last HW.
+        num: Option<usize>,
+
+        /// Print a JSON description of the HW directory and its files.
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Export the LyX file of a HW directory (e.g. to PDF), and print the path of the exported file.
+    Export {
+        /// Name of the subject, or its index in the subjects list.
+        subject: String,
+
+        /// The HW number. Defaults to the last HW.
+        num: Option<usize>,
+    },
+
+    /// Package a HW directory for submission, and print the path of the package.
+    ///
+    /// If checks are configured, they have to pass first
+    /// (with `--dry-run`, their results are printed along with the plan instead).
+    Package {
+        /// Name of the subject, or its index in the subjects list.
+        subject: String,
+
+        /// The HW number. Defaults to the last HW.
+        num: Option<usize>,
+    },
+
+    /// Run the checks configured in the settings on a HW directory, and fail if any of them fails.
+    Check {
+        /// Name of the subject, or its index in the subjects list.
+        subject: String,
+
+        /// The HW number. Defaults to the last HW.
+        num: Option<usize>,
+    },
+
+    /// Look for problems in the settings and in the subjects, and fail if there are any.
+    ///
+    /// Reports missing directories, unreadable template files, openers that are not found on PATH,
+    /// invalid per-subject settings, and subject directories that cannot be loaded.
+    Doctor,
+
+    /// Print the effective settings, as JSON.
+    Settings {
+        /// Print the settings of this subject (including its per-subject settings file).
+        subject: Option<String>,
+    },
+}
+
+impl Command {
+    pub fn run(&self, settings: &Settings, dry_run: bool) -> anyhow::Result<()> {
+        match self {
+            Command::List { json } => {
+                let subjects = Subject::get_all_subjects(settings)?;
+                if *json {
+                    let views = subjects
+                        .iter()
+                        .map(Subject::view)
+                        .collect::<anyhow::Result<Vec<_>>>()?;
+                    println!("{}", serde_json::to_string_pretty(&views)?);
+                } else {
+                    for (i, subject) in subjects.iter().enumerate() {
+                        println!("{i}\t{}\t{}", subject.name(), subject.current_hw_num());
+                    }
+                }
+            }
+            Command::New {
+                subject,
+                questions_file,
+                attachments,
+            } => {
+                let mut subject = find_subject(settings, subject)?;
+                let options = NewHwOptions {
+                    questions_file: questions_file.clone(),
+                    attachments: (!attachments.is_empty()).then(|| attachments.clone().into()),
+                };
+                if dry_run {
+                    print!("{}", subject.plan_new_hw_dir(&options)?);
+                } else {
+                    subject.create_new_hw_dir(&options)?;
+                    println!("{}", subject.hw_dir(subject.current_hw_num())?.display());
+                }
+            }
+            Command::Open { subject, num } => {
+                let subject = find_subject(settings, subject)?;
+                let plan = subject.plan_open(num.unwrap_or(subject.current_hw_num()))?;
+                if dry_run {
+                    print!("{plan}");
+                } else {
+                    subject.open(&plan)?;
+                }
+            }
+            Command::Path { subject, num, json } => {
+                let subject = find_subject(settings, subject)?;
+                let num = num.unwrap_or(subject.current_hw_num());
+                if *json {
+                    println!("{}", serde_json::to_string_pretty(&subject.hw_view(num)?)?);
+                } else {
+                    println!("{}", subject.hw_dir(num)?.display());
+                }
+            }
+            Command::Export { subject, num } => {
+                let subject = find_subject(settings, subject)?;
+                let plan = subject.plan_export(num.unwrap_or(subject.current_hw_num()))?;
+                if dry_run {
+                    print!("{plan}");
+                } else {
+                    subject.export(&plan)?;
+                    println!("{}", plan.output().display());
+                }
+            }
+            Command::Package { subject, num } => {
+                let subject = find_subject(settings, subject)?;
+                let num = num.unwrap_or(subject.current_hw_num());
+                let report = subject
+                    .settings()
+                    .checks_settings()
+                    .map(|_| subject.check(num))
+                    .transpose()?;
+                let plan = subject.plan_package(num)?;
+                if dry_run {
+                    if let Some(report) = report {
+                        print!("{report}");
+                    }
+                    print!("{plan}");
+                } else {
+                    if let Some(report) = report.filter(|report| !report.passed()) {
+                        anyhow::bail!("checks failed:\n{}", report.to_string().trim_end());
+                    }
+                    subject.package(&plan)?;
+                    println!("{}", plan.output().display());
+                }
+            }
+            Command::Check { subject, num } => {
+                let subject = find_subject(settings, subject)?;
+                let report = subject.check(num.unwrap_or(subject.current_hw_num()))?;
+                print!("{report}");
+                if !report.passed() {
+                    anyhow::bail!("some checks failed");
+                }
+            }
+            Command::Doctor => {
+                let report = Subject::doctor(settings)?;
+                print!("{report}");
+                if !report.healthy() {
+                    anyhow::bail!("some problems were found");
+                }
+            }
+            Command::Settings { subject } => {
+                let settings = match subject {
+                    Some(subject) => find_subject(settings, subject)?.settings().clone(),
+                    None => settings.clone(),
+                };
+                println!("{}", serde_json::to_string_pretty(&settings)?);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn find_subject(settings: &Settings, name_or_index: &str) -> anyhow::Result<Subject> {
+    let mut subjects = Subject::get_all_subjects(settings)?;
+    if let Some(subject) = Subject::find(&mut subjects, name_or_index) {
+        return Ok(subject.clone());
+    }
+    // subjects that cannot be loaded are not listed, so explain why instead
+    let dir = settings.subjects_dir().join(name_or_index);
+    if dir.is_dir() {
+        Subject::from_directory(settings, dir)
+            .map_err(|err| anyhow::anyhow!("cannot load subject {name_or_index}: {err:#}"))
+    } else {
+        anyhow::bail!("no such subject: {name_or_index}")
+    }
+}
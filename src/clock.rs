@@ -0,0 +1,59 @@
+use chrono::{DateTime, Local, TimeZone};
+
+/// Environment variable that fixes the current time (as seconds since the Unix epoch),
+/// so that the generated output is reproducible.
+const SOURCE_DATE_EPOCH: &str = "SOURCE_DATE_EPOCH";
+
+/// The source of the current time, used for the date and time arguments of format specifiers.
+#[derive(Clone, Copy, Default)]
+pub enum Clock {
+    #[default]
+    System,
+    Fixed(DateTime<Local>),
+}
+
+impl Clock {
+    /// A fixed clock if `SOURCE_DATE_EPOCH` is set, otherwise the system clock.
+    pub fn from_env() -> anyhow::Result<Self> {
+        let Ok(epoch) = std::env::var(SOURCE_DATE_EPOCH) else {
+            return Ok(Self::System);
+        };
+        let secs: i64 = epoch
+            .trim()
+            .parse()
+            .map_err(|err| anyhow::anyhow!("invalid {SOURCE_DATE_EPOCH} {epoch:?}: {err}"))?;
+        let now = Local
+            .timestamp_opt(secs, 0)
+            .single()
+            .ok_or_else(|| anyhow::anyhow!("invalid {SOURCE_DATE_EPOCH} {epoch:?}"))?;
+
+        Ok(Self::Fixed(now))
+    }
+
+    pub fn now(&self) -> DateTime<Local> {
+        match self {
+            Self::System => Local::now(),
+            Self::Fixed(now) => *now,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_env() {
+        // the only test that touches SOURCE_DATE_EPOCH
+        std::env::set_var(SOURCE_DATE_EPOCH, "1700000000");
+        let clock = Clock::from_env().unwrap();
+        assert_eq!(clock.now().timestamp(), 1_700_000_000);
+        assert_eq!(clock.now(), clock.now());
+
+        std::env::set_var(SOURCE_DATE_EPOCH, "yesterday");
+        assert!(Clock::from_env().is_err());
+
+        std::env::remove_var(SOURCE_DATE_EPOCH);
+        assert!(matches!(Clock::from_env().unwrap(), Clock::System));
+    }
+}
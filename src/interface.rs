@@ -7,7 +7,9 @@ use serde::{Deserialize, Serialize};
 use tui_interface::TuiInterface;
 
 pub trait Interface {
-    fn main(settings: &Settings) -> anyhow::Result<()>;
+    /// Run the interface.
+    /// If `dry_run` is set, creating a new HW directory only shows what would be done.
+    fn main(settings: &Settings, dry_run: bool) -> anyhow::Result<()>;
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -19,10 +21,10 @@ pub enum InterfaceType {
 }
 
 impl InterfaceType {
-    pub fn main(&self, settings: &Settings) -> anyhow::Result<()> {
+    pub fn main(&self, settings: &Settings, dry_run: bool) -> anyhow::Result<()> {
         match self {
-            InterfaceType::Cmd => CmdInterface::main(settings),
-            InterfaceType::Tui => TuiInterface::main(settings),
+            InterfaceType::Cmd => CmdInterface::main(settings, dry_run),
+            InterfaceType::Tui => TuiInterface::main(settings, dry_run),
         }
     }
 }
@@ -1,19 +1,24 @@
 use super::Interface;
 use crate::settings::Settings;
-use crate::subject::Subject;
-use std::io::{self, Write};
+use crate::subject::{NewHwOptions, Subject};
+use std::{
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
 
 pub struct CmdInterface;
 
 impl Interface for CmdInterface {
-    fn main(settings: &Settings) -> anyhow::Result<()> {
+    fn main(settings: &Settings, dry_run: bool) -> anyhow::Result<()> {
         let mut subjects = Subject::get_all_subjects(settings)?;
 
         println!("List of available subjects:");
         for (i, subject) in subjects.iter().enumerate() {
             println!(
                 "\t{i}. {}",
-                settings.interface_settings().subject_label(subject)?,
+                settings
+                    .interface_settings()
+                    .subject_label(subject, &subject.settings().format_context())?,
             );
         }
 
@@ -29,13 +34,7 @@ impl Interface for CmdInterface {
                 input.replace_range(0..3, "");
             }
 
-            if let Ok(i) = input.parse::<usize>() {
-                if i < subjects.len() {
-                    break (open, &mut subjects[i]);
-                }
-            }
-
-            if let Some(subject) = subjects.iter_mut().find(|subject| subject.name() == input) {
+            if let Some(subject) = Subject::find(&mut subjects, &input) {
                 break (open, subject);
             }
 
@@ -46,10 +45,97 @@ impl Interface for CmdInterface {
 
         if open {
             subject.open_last_hw()?;
+            return Ok(());
+        }
+
+        let questions_file = choose_questions_file(subject)?;
+        let attachments = choose_attachments(subject, questions_file.as_deref())?;
+        let options = NewHwOptions {
+            questions_file,
+            attachments,
+        };
+        if dry_run {
+            print!("{}", subject.plan_new_hw_dir(&options)?);
         } else {
-            subject.create_new_hw_dir()?;
+            subject.create_new_hw_dir(&options)?;
         }
 
         Ok(())
     }
 }
+
+/// Let the user choose the questions file, if the settings of the subject ask for it.
+fn choose_questions_file(subject: &Subject) -> anyhow::Result<Option<PathBuf>> {
+    let Some(choices) = subject.questions_file_choices()? else {
+        return Ok(None);
+    };
+    if choices.is_empty() {
+        return Ok(None);
+    }
+
+    println!("Recent downloads:");
+    for (i, download) in choices.iter().enumerate() {
+        println!("\t{i}. {download}");
+    }
+
+    print!("Choose the questions file (default 0): ");
+    io::stdout().flush()?;
+    loop {
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+        let input = input.trim();
+
+        if input.is_empty() {
+            return Ok(Some(choices[0].path().to_owned()));
+        }
+        if let Some(download) = input.parse::<usize>().ok().and_then(|i| choices.get(i)) {
+            return Ok(Some(download.path().to_owned()));
+        }
+
+        println!("That was not one of the options...");
+        print!("Try again: ");
+        io::stdout().flush()?;
+    }
+}
+
+/// Let the user choose the attachments, if the settings of the subject ask for it.
+fn choose_attachments(
+    subject: &Subject,
+    questions_file: Option<&Path>,
+) -> anyhow::Result<Option<Box<[PathBuf]>>> {
+    let Some(choices) = subject.attachment_choices(questions_file)? else {
+        return Ok(None);
+    };
+    if choices.is_empty() {
+        return Ok(None);
+    }
+
+    println!("Other recent downloads:");
+    for (i, download) in choices.iter().enumerate() {
+        println!("\t{i}. {download}");
+    }
+
+    print!("Choose the attachments (comma-separated, default none): ");
+    io::stdout().flush()?;
+    loop {
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+
+        let attachments: Option<Box<[_]>> = input
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                let download = choices.get(s.parse::<usize>().ok()?)?;
+                Some(download.path().to_owned())
+            })
+            .collect();
+        if let Some(attachments) = attachments {
+            return Ok(Some(attachments));
+        }
+
+        println!("That was not one of the options...");
+        print!("Try again: ");
+        io::stdout().flush()?;
+    }
+}
@@ -1,26 +1,31 @@
 use super::Interface;
 use crate::settings::Settings;
-use crate::subject::Subject;
+use crate::subject::{NewHwOptions, Subject};
 use cursive::{
     align::HAlign,
     event::{Callback, Event, EventResult, Key::Esc},
     utils::markup::StyledString,
-    view::{Nameable, Scrollable},
+    view::{Nameable, Scrollable, View},
     views::{
-        Dialog, LinearLayout, NamedView, OnEventView, PaddedView, SelectView, TextView, ViewRef,
+        Checkbox, Dialog, LinearLayout, NamedView, OnEventView, PaddedView, SelectView, TextView,
+        ViewRef,
     },
     Cursive, With,
 };
-use std::ops::DerefMut;
+use std::{ops::DerefMut, path::PathBuf};
 
 pub struct TuiInterface;
 
 impl Interface for TuiInterface {
-    fn main(settings: &Settings) -> anyhow::Result<()> {
+    fn main(settings: &Settings, dry_run: bool) -> anyhow::Result<()> {
         let subjects: Box<[Subject]> = Subject::get_all_subjects(settings)?;
         let subject_labels = subjects
             .iter()
-            .map(|subject| settings.interface_settings().subject_label(subject))
+            .map(|subject| {
+                settings
+                    .interface_settings()
+                    .subject_label(subject, &subject.settings().format_context())
+            })
             .collect::<Result<Box<[_]>, _>>()?;
 
         let mut siv = cursive::default();
@@ -38,56 +43,49 @@ impl Interface for TuiInterface {
             .on_submit({
                 let settings = settings.clone();
                 move |siv, subject: &Subject| {
-                    siv.add_layer(
-                        OnEventView::new(
-                            Dialog::text("Pick an action:")
-                                .title(subject.name())
-                                .button("Cancel", move |siv| {
+                    siv.add_layer(closable(
+                        Dialog::text("Pick an action:")
+                            .title(subject.name())
+                            .button("Cancel", move |siv| {
+                                siv.pop_layer();
+                            })
+                            .button("Open", move |siv| {
+                                let mut select: ViewRef<SelectView<Subject>> =
+                                    siv.find_name("select").unwrap();
+                                let (_, subject) = selected(select.deref_mut());
+                                if let Err(err) = subject.open_last_hw() {
+                                    error(siv, &err);
+                                } else {
                                     siv.pop_layer();
-                                })
-                                .button("Open", move |siv| {
-                                    let mut select: ViewRef<SelectView<Subject>> =
-                                        siv.find_name("select").unwrap();
-                                    let (_, subject) = selected(select.deref_mut());
-                                    if let Err(err) = subject.open_last_hw() {
-                                        error(siv, &err);
-                                    } else {
-                                        siv.pop_layer();
-                                    }
-                                })
-                                .button("New", {
-                                    let settings = settings.clone();
-                                    move |siv| {
-                                        let mut select: ViewRef<SelectView<Subject>> =
-                                            siv.find_name("select").unwrap();
-                                        let (label, subject) = selected(select.deref_mut());
-
-                                        match subject.create_new_hw_dir().and_then(|()| {
-                                            settings
-                                                .interface_settings()
-                                                .subject_label(subject)
-                                                .map_err(Into::into)
-                                        }) {
-                                            Ok(new_label) => {
-                                                *label = new_label.into();
-                                                siv.pop_layer();
-                                            }
-                                            Err(err) => {
-                                                error(siv, &err);
-                                            }
-                                        }
-                                    }
-                                }),
-                        )
-                        .on_pre_event_inner(
-                            Event::Key(Esc),
-                            move |_, _| {
-                                Some(EventResult::Consumed(Some(Callback::from_fn(move |siv| {
+                                }
+                            })
+                            .button("New", {
+                                let settings = settings.clone();
+                                move |siv| {
                                     siv.pop_layer();
-                                }))))
-                            },
-                        ),
-                    )
+                                    new_hw(siv, &settings, dry_run);
+                                }
+                            })
+                            .button("Preview", {
+                                let settings = settings.clone();
+                                move |siv| {
+                                    siv.pop_layer();
+                                    new_hw(siv, &settings, true);
+                                }
+                            })
+                            .button("Browse", |siv| {
+                                siv.pop_layer();
+                                browse_hws(siv);
+                            })
+                            .button("Export", |siv| {
+                                siv.pop_layer();
+                                export_last_hw(siv);
+                            })
+                            .button("Check", |siv| {
+                                siv.pop_layer();
+                                check_last_hw(siv);
+                            }),
+                    ))
                 }
             })
             .with_name("select");
@@ -112,29 +110,33 @@ impl Interface for TuiInterface {
             )
             .on_pre_event_inner(Event::CtrlChar('n'), {
                 let settings = settings.clone();
-                move |select, _| {
-                    let mut select = select.get_mut();
-                    let Some((label, subject)) = try_selected(select.deref_mut()) else {
-                        return Some(EventResult::Consumed(None));
-                    };
-
-                    match subject.create_new_hw_dir().and_then(|()| {
-                        settings
-                            .interface_settings()
-                            .subject_label(subject)
-                            .map_err(Into::into)
-                    }) {
-                        Ok(new_label) => *label = new_label.into(),
-                        Err(err) => {
-                            return Some(EventResult::Consumed(Some(Callback::from_fn(
-                                move |siv| {
-                                    error(siv, &err);
-                                },
-                            ))))
-                        }
-                    }
-
-                    Some(EventResult::Consumed(None))
+                move |_, _| {
+                    let settings = settings.clone();
+                    Some(EventResult::Consumed(Some(Callback::from_fn(move |siv| {
+                        new_hw(siv, &settings, dry_run)
+                    }))))
+                }
+            })
+            .on_pre_event_inner(Event::CtrlChar('b'), |_, _| {
+                Some(EventResult::Consumed(Some(Callback::from_fn(browse_hws))))
+            })
+            .on_pre_event_inner(Event::CtrlChar('e'), |_, _| {
+                Some(EventResult::Consumed(Some(Callback::from_fn(
+                    export_last_hw,
+                ))))
+            })
+            .on_pre_event_inner(Event::CtrlChar('k'), |_, _| {
+                Some(EventResult::Consumed(Some(Callback::from_fn(
+                    check_last_hw,
+                ))))
+            })
+            .on_pre_event_inner(Event::CtrlChar('p'), {
+                let settings = settings.clone();
+                move |_, _| {
+                    let settings = settings.clone();
+                    Some(EventResult::Consumed(Some(Callback::from_fn(move |siv| {
+                        new_hw(siv, &settings, true)
+                    }))))
                 }
             });
 
@@ -151,6 +153,10 @@ impl Interface for TuiInterface {
                             "Press <Enter> to select a subject.\n",
                             "Press <Ctrl+O> to open the last HW directory.\n",
                             "Press <Ctrl+N> to create a new HW directory.\n",
+                            "Press <Ctrl+P> to preview creating a new HW directory.\n",
+                            "Press <Ctrl+B> to browse the HW directories.\n",
+                            "Press <Ctrl+E> to export the last HW.\n",
+                            "Press <Ctrl+K> to check the last HW before submitting it.\n",
                             "Press <Ctrl+C> to exit.",
                         )
                     )
@@ -163,6 +169,288 @@ impl Interface for TuiInterface {
     }
 }
 
+/// Create a new HW directory in the selected subject.
+/// If `dry_run` is set, only show what would be done, and let the user confirm it.
+fn new_hw(siv: &mut Cursive, settings: &Settings, dry_run: bool) {
+    let settings = settings.clone();
+    choose_questions_file(siv, move |siv, options| {
+        let settings = settings.clone();
+        choose_attachments(siv, options.clone(), move |siv, options| {
+            if dry_run {
+                preview_new_hw(siv, &settings, options);
+            } else {
+                create_new_hw(siv, &settings, options);
+            }
+        });
+    });
+}
+
+/// Let the user choose the questions file (if the settings of the selected subject ask for it),
+/// then continue with the chosen options.
+fn choose_questions_file(
+    siv: &mut Cursive,
+    then: impl Fn(&mut Cursive, &NewHwOptions) + Send + Sync + 'static,
+) {
+    let mut select: ViewRef<SelectView<Subject>> = siv.find_name("select").unwrap();
+    let Some((_, subject)) = try_selected(select.deref_mut()) else {
+        return;
+    };
+
+    let choices = match subject.questions_file_choices() {
+        Ok(Some(choices)) if !choices.is_empty() => choices,
+        Ok(_) => {
+            drop(select);
+            return then(siv, &NewHwOptions::default());
+        }
+        Err(err) => return error(siv, &err),
+    };
+
+    let download_select = SelectView::new()
+        .with_all(
+            choices
+                .iter()
+                .map(|download| (download.to_string(), download.path().to_owned())),
+        )
+        .on_submit(move |siv, questions_file: &PathBuf| {
+            siv.pop_layer();
+            then(
+                siv,
+                &NewHwOptions {
+                    questions_file: Some(questions_file.clone()),
+                    ..Default::default()
+                },
+            );
+        });
+
+    siv.add_layer(closable(
+        Dialog::around(download_select.scrollable())
+            .title("Pick the questions file")
+            .button("Cancel", |siv| {
+                siv.pop_layer();
+            }),
+    ));
+}
+
+/// Let the user choose the attachments (if the settings of the selected subject ask for it),
+/// then continue with the chosen options.
+fn choose_attachments(
+    siv: &mut Cursive,
+    options: NewHwOptions,
+    then: impl Fn(&mut Cursive, &NewHwOptions) + Send + Sync + 'static,
+) {
+    let mut select: ViewRef<SelectView<Subject>> = siv.find_name("select").unwrap();
+    let Some((_, subject)) = try_selected(select.deref_mut()) else {
+        return;
+    };
+
+    let choices = match subject.attachment_choices(options.questions_file.as_deref()) {
+        Ok(Some(choices)) if !choices.is_empty() => choices,
+        Ok(_) => {
+            drop(select);
+            return then(siv, &options);
+        }
+        Err(err) => return error(siv, &err),
+    };
+
+    let checkboxes = LinearLayout::vertical().with(|checkboxes| {
+        for (i, download) in choices.iter().enumerate() {
+            checkboxes.add_child(
+                LinearLayout::horizontal()
+                    .child(Checkbox::new().with_name(format!("attachment{i}")))
+                    .child(TextView::new(format!(" {download}"))),
+            );
+        }
+    });
+    let paths: Box<[PathBuf]> = choices
+        .iter()
+        .map(|download| download.path().to_owned())
+        .collect();
+
+    siv.add_layer(closable(
+        Dialog::around(checkboxes.scrollable())
+            .title("Pick the attachments")
+            .button("Cancel", |siv| {
+                siv.pop_layer();
+            })
+            .button("OK", move |siv| {
+                let attachments = paths
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| {
+                        siv.find_name::<Checkbox>(&format!("attachment{i}"))
+                            .is_some_and(|checkbox| checkbox.is_checked())
+                    })
+                    .map(|(_, path)| path.clone())
+                    .collect();
+                siv.pop_layer();
+                then(
+                    siv,
+                    &NewHwOptions {
+                        attachments: Some(attachments),
+                        ..options.clone()
+                    },
+                );
+            }),
+    ));
+}
+
+fn create_new_hw(siv: &mut Cursive, settings: &Settings, options: &NewHwOptions) {
+    let mut select: ViewRef<SelectView<Subject>> = siv.find_name("select").unwrap();
+    let Some((label, subject)) = try_selected(select.deref_mut()) else {
+        return;
+    };
+
+    match subject.create_new_hw_dir(options).and_then(|()| {
+        settings
+            .interface_settings()
+            .subject_label(subject, &subject.settings().format_context())
+    }) {
+        Ok(new_label) => *label = new_label.into(),
+        Err(err) => error(siv, &err),
+    }
+}
+
+/// Show what creating a new HW directory in the selected subject would do,
+/// and let the user confirm it.
+fn preview_new_hw(siv: &mut Cursive, settings: &Settings, options: &NewHwOptions) {
+    let mut select: ViewRef<SelectView<Subject>> = siv.find_name("select").unwrap();
+    let Some((_, subject)) = try_selected(select.deref_mut()) else {
+        return;
+    };
+
+    let plan = match subject.plan_new_hw_dir(options) {
+        Ok(plan) => plan,
+        Err(err) => return error(siv, &err),
+    };
+    siv.add_layer(closable(
+        Dialog::text(plan.to_string())
+            .title(format!("New HW in {}", subject.name()))
+            .button("Cancel", |siv| {
+                siv.pop_layer();
+            })
+            .button("Create", {
+                let settings = settings.clone();
+                move |siv| {
+                    let mut select: ViewRef<SelectView<Subject>> = siv.find_name("select").unwrap();
+                    let (label, subject) = selected(select.deref_mut());
+
+                    match subject.create_new_hw_dir_from_plan(&plan).and_then(|()| {
+                        settings
+                            .interface_settings()
+                            .subject_label(subject, &subject.settings().format_context())
+                    }) {
+                        Ok(new_label) => {
+                            *label = new_label.into();
+                            siv.pop_layer();
+                        }
+                        Err(err) => {
+                            error(siv, &err);
+                        }
+                    }
+                }
+            }),
+    ));
+}
+
+/// Show the list of HW directories in the selected subject, and let the user open any of them.
+fn browse_hws(siv: &mut Cursive) {
+    let mut select: ViewRef<SelectView<Subject>> = siv.find_name("select").unwrap();
+    let Some((_, subject)) = try_selected(select.deref_mut()) else {
+        return;
+    };
+
+    let hws = match subject.list_hws() {
+        Ok(hws) => hws,
+        Err(err) => return error(siv, &err),
+    };
+    let title = format!("HWs in {}", subject.name());
+    let subject = subject.clone();
+
+    let hw_select = SelectView::new()
+        .with_all(hws.into_vec().into_iter().rev().map(|(num, hw_dir)| {
+            let label = hw_dir.file_name().map_or_else(
+                || num.to_string(),
+                |name| name.to_string_lossy().into_owned(),
+            );
+            (label, num)
+        }))
+        .h_align(HAlign::Center)
+        .on_submit(move |siv, num: &usize| {
+            if let Err(err) = subject.open_hw(*num) {
+                error(siv, &err);
+            } else {
+                siv.pop_layer();
+            }
+        });
+
+    siv.add_layer(closable(
+        Dialog::around(hw_select.scrollable())
+            .title(title)
+            .button("Cancel", |siv| {
+                siv.pop_layer();
+            }),
+    ));
+}
+
+/// Export the LyX file of the last HW in the selected subject, and report the result.
+fn export_last_hw(siv: &mut Cursive) {
+    let mut select: ViewRef<SelectView<Subject>> = siv.find_name("select").unwrap();
+    let Some((_, subject)) = try_selected(select.deref_mut()) else {
+        return;
+    };
+
+    let plan = match subject.plan_export(subject.current_hw_num()) {
+        Ok(plan) => plan,
+        Err(err) => return error(siv, &err),
+    };
+    // errors of the export itself (e.g. LyX errors) are not fatal
+    let (title, text) = match subject.export(&plan) {
+        Ok(()) => ("Exported", plan.output().display().to_string()),
+        Err(err) => ("Export failed", format!("{err:#}")),
+    };
+    drop(select);
+
+    siv.add_layer(closable(
+        Dialog::around(TextView::new(text).scrollable())
+            .title(title)
+            .button("OK", |siv| {
+                siv.pop_layer();
+            }),
+    ));
+}
+
+/// Run the checks on the last HW in the selected subject, and show the results.
+fn check_last_hw(siv: &mut Cursive) {
+    let mut select: ViewRef<SelectView<Subject>> = siv.find_name("select").unwrap();
+    let Some((_, subject)) = try_selected(select.deref_mut()) else {
+        return;
+    };
+
+    let (title, text) = match subject.check(subject.current_hw_num()) {
+        Ok(report) if report.passed() => ("All checks passed", report.to_string()),
+        Ok(report) => ("Some checks failed", report.to_string()),
+        Err(err) => ("Checking failed", format!("{err:#}")),
+    };
+    drop(select);
+
+    siv.add_layer(closable(
+        Dialog::around(TextView::new(text).scrollable())
+            .title(title)
+            .button("OK", |siv| {
+                siv.pop_layer();
+            }),
+    ));
+}
+
+/// Make `<Esc>` close the given layer.
+fn closable<V: View>(view: V) -> OnEventView<V> {
+    OnEventView::new(view).on_pre_event_inner(Event::Key(Esc), move |_, _| {
+        Some(EventResult::Consumed(Some(Callback::from_fn(move |siv| {
+            siv.pop_layer();
+        }))))
+    })
+}
+
 fn try_selected(select: &mut SelectView<Subject>) -> Option<(&mut StyledString, &mut Subject)> {
     let i = select.selected_id()?;
     select.get_item_mut(i)
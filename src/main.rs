@@ -1,9 +1,16 @@
-use config::File;
+use clap::Parser;
+use cli::{Cli, Command};
+use clock::Clock;
 use settings::Settings;
+use std::process::ExitCode;
 
+mod cli;
+mod clock;
 mod interface;
 mod settings;
 mod subject;
+#[cfg(test)]
+mod test_utils;
 
 const SETTINGS_FILE: &str = if cfg!(debug_assertions) {
     "example_settings.json5"
@@ -11,20 +18,31 @@ const SETTINGS_FILE: &str = if cfg!(debug_assertions) {
     "settings.json5"
 };
 
-fn main() -> anyhow::Result<()> {
-    let settings = Settings::new([File::with_name(SETTINGS_FILE)])?;
+fn main() -> ExitCode {
+    let cli = Cli::parse();
 
-    match settings
-        .interface_settings()
-        .interface_type()
-        .main(&settings)
-    {
-        Ok(()) => {}
+    match run(&cli) {
+        Ok(()) => ExitCode::SUCCESS,
         Err(e) => {
-            eprintln!("ERROR: {e}");
-            anyhow::bail!(e);
+            eprintln!("ERROR: {e:#}");
+            ExitCode::FAILURE
         }
     }
+}
+
+fn run(cli: &Cli) -> anyhow::Result<()> {
+    let settings = match cli.command {
+        // the doctor reports invalid format specifiers along with the other problems
+        Some(Command::Doctor) => Settings::load(SETTINGS_FILE)?,
+        _ => Settings::new(SETTINGS_FILE)?,
+    }
+    .with_clock(Clock::from_env()?);
 
-    Ok(())
+    match &cli.command {
+        Some(command) => command.run(&settings, cli.dry_run),
+        None => settings
+            .interface_settings()
+            .interface_type()
+            .main(&settings, cli.dry_run),
+    }
 }
@@ -1,26 +1,52 @@
-use config::{Config, File, FileFormat, Source};
-use formatx::formatx;
+use crate::clock::Clock;
+use config::{Config, File, FileFormat};
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
 
+pub mod archive_settings;
+pub mod attachments_settings;
+mod checks_settings;
+pub mod document_settings;
+pub mod download_filter;
+pub mod export_settings;
+pub mod format_context;
+pub mod format_matcher;
+mod format_string;
 mod interface_settings;
+pub mod lyx_edit_settings;
 mod lyx_file_settings;
 pub mod open_settings;
+pub mod profile_settings;
 mod questions_file_settings;
+mod semester_settings;
 mod subject_ordering;
+mod submission_settings;
+pub mod template_dir_settings;
 
+use checks_settings::ChecksSettings;
+use document_settings::DocumentSettings;
+use export_settings::ExportSettings;
+use format_context::FormatContext;
+use format_matcher::FormatMatcher;
+use format_string::{FormatFields, FormatString};
 use interface_settings::InterfaceSettings;
 use lyx_file_settings::LyxFileSettings;
+use profile_settings::ProfileSettings;
 use questions_file_settings::QuestionsFileSettings;
+use semester_settings::SemesterSettings;
 use subject_ordering::SubjectOrdering;
+use submission_settings::SubmissionSettings;
+use template_dir_settings::TemplateDirSettings;
 
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Settings {
     // mandatory:
     subjects_dir: Box<Path>,
-    hw_dir_format: Box<str>,
-    max_hw_dirs: usize,
+    hw_dir_format: FormatString,
     open_after_creation: bool,
     interface: InterfaceSettings,
 
@@ -28,52 +54,211 @@ pub struct Settings {
     subject_ordering: SubjectOrdering,
 
     // optional
+    max_hw_dirs: Option<usize>,
     subject_settings_filename: Option<Box<str>>,
     hebrew_name: Option<Box<str>>,
+    #[serde(default)]
+    profile: ProfileSettings,
+    #[serde(default)]
+    variables: BTreeMap<Box<str>, Box<str>>,
+    semester: Option<SemesterSettings>,
 
     // questions file:
     questions_file: Option<QuestionsFileSettings>,
 
-    // LyX file:
+    // documents:
+    template_dir: Option<TemplateDirSettings>,
     lyx_file: Option<LyxFileSettings>,
+    #[serde(default)]
+    documents: Box<[DocumentSettings]>,
+
+    // export and submission:
+    export: Option<ExportSettings>,
+    submission: Option<SubmissionSettings>,
+    checks: Option<ChecksSettings>,
+
+    #[serde(skip)]
+    clock: Clock,
+    /// The file these settings were last loaded from.
+    #[serde(skip)]
+    settings_file: PathBuf,
 }
 
 impl Settings {
-    pub fn new(
-        sources: impl IntoIterator<Item: Source + Send + Sync + 'static>,
-    ) -> anyhow::Result<Self> {
-        let mut builder = Config::builder();
-        for source in sources {
-            builder = builder.add_source(source);
-        }
-        Ok(builder.build()?.try_deserialize()?)
+    /// Load the settings from `settings_file` (the extension may be omitted), and check them
+    /// (see [`Self::validate_global`]).
+    pub fn new(settings_file: &str) -> anyhow::Result<Self> {
+        let settings = Self::load(settings_file)?;
+        settings.validate_global()?;
+        Ok(settings)
+    }
+
+    /// Load the settings from `settings_file`, like [`Self::new`], without checking the format specifiers.
+    pub fn load(settings_file: &str) -> anyhow::Result<Self> {
+        let settings: Self = Config::builder()
+            .add_source(File::with_name(settings_file))
+            .build()?
+            .try_deserialize()?;
+        Ok(Self {
+            settings_file: settings_file.into(),
+            ..settings
+        })
     }
 
-    pub fn update(
-        &self,
-        sources: impl IntoIterator<Item: Source + Send + Sync + 'static>,
-    ) -> anyhow::Result<Self> {
-        let mut builder = Config::builder().add_source(File::from_str(
-            &serde_json::to_string(self)?,
-            FileFormat::Json,
-        ));
+    /// Use `clock` for the date and time arguments of format specifiers.
+    pub fn with_clock(self, clock: Clock) -> Self {
+        Self { clock, ..self }
+    }
+
+    /// Override the settings with those in `settings_file`, and check the resulting settings entirely.
+    ///
+    /// An invalid format specifier that is not overridden is reported as coming from the file of these settings.
+    pub fn update(&self, settings_file: &Path) -> anyhow::Result<Self> {
+        let updated = self.merge(settings_file)?;
+        updated.validate(Some(&[]), Some(self))?;
+        Ok(updated)
+    }
+
+    /// Override the settings with those in `settings_file`, without checking the format specifiers.
+    fn merge(&self, settings_file: &Path) -> anyhow::Result<Self> {
+        let updated: Self = Config::builder()
+            .add_source(File::from_str(
+                &serde_json::to_string(self)?,
+                FileFormat::Json,
+            ))
+            .add_source(File::from(settings_file))
+            .build()?
+            .try_deserialize()?;
+        Ok(Self {
+            clock: self.clock,
+            settings_file: settings_file.to_owned(),
+            ..updated
+        })
+    }
+
+    /// Check the format specifiers of the global settings.
+    ///
+    /// A format specifier may use a named argument that only some of the per-subject settings files define
+    /// (e.g. a variable); whether every subject defines it is checked along with the subject's settings.
+    pub fn validate_global(&self) -> anyhow::Result<()> {
+        let subject_args = self.subject_args();
+        let subject_args: Vec<_> = subject_args.iter().map(String::as_str).collect();
+        self.validate(Some(&subject_args), None)
+    }
+
+    /// Check that every format specifier only uses the named arguments available to it.
+    pub fn validate_args(&self) -> anyhow::Result<()> {
+        self.validate(Some(&[]), None)
+    }
 
-        for source in sources {
-            builder = builder.add_source(source);
+    /// The named arguments that the per-subject settings files add to the format context.
+    /// Settings files that cannot be loaded are skipped (they are reported when loading their subject).
+    fn subject_args(&self) -> BTreeSet<String> {
+        let Some(subject_settings_filename) = self.subject_settings_filename() else {
+            return BTreeSet::new();
+        };
+        let Ok(subject_dirs) = self.subjects_dir.read_dir() else {
+            return BTreeSet::new();
+        };
+        let global_args = self.format_context().args().clone();
+        subject_dirs
+            .filter_map(Result::ok)
+            .map(|entry| entry.path().join(subject_settings_filename))
+            .filter(|path| path.is_file())
+            .filter_map(|path| self.merge(&path).ok())
+            .flat_map(|settings| settings.format_context().args().clone().into_keys())
+            .filter(|name| !global_args.contains_key(name))
+            .collect()
+    }
+
+    /// The format specifiers in the settings.
+    fn format_fields(&self) -> FormatFields<'_> {
+        let mut fields = FormatFields::default();
+        fields.add("hw_dir_format", &self.hw_dir_format, &["num"]);
+        self.interface.format_fields("interface", &mut fields);
+        if let Some(questions_file) = &self.questions_file {
+            questions_file.format_fields("questions_file", &mut fields);
+        }
+        if let Some(template_dir) = &self.template_dir {
+            template_dir.format_fields("template_dir", &mut fields);
+        }
+        if let Some(lyx_file) = &self.lyx_file {
+            lyx_file.format_fields("lyx_file", &mut fields);
         }
+        for (i, document) in self.documents.iter().enumerate() {
+            document.format_fields(&format!("documents[{i}]"), &mut fields);
+        }
+        if let Some(export) = &self.export {
+            export.format_fields("export", &mut fields);
+        }
+        if let Some(submission) = &self.submission {
+            submission.format_fields("submission", &mut fields);
+        }
+        fields
+    }
 
-        Ok(builder.build()?.try_deserialize()?)
+    /// Check every format specifier, reporting the settings file and key of the first invalid one.
+    ///
+    /// With `extra_args`, also check that the format specifier only uses the named arguments available to it
+    /// (or those in `extra_args`).
+    /// A format specifier that is the same as in `base` is reported as coming from the file of `base`.
+    fn validate(&self, extra_args: Option<&[&str]>, base: Option<&Settings>) -> anyhow::Result<()> {
+        let base_fields = base.map(Settings::format_fields);
+        let context = self.format_context();
+        for (key, format, args) in self.format_fields().iter() {
+            let result = context.validate(format).and_then(|()| match extra_args {
+                Some(extra_args) => {
+                    let args: Vec<_> = args.iter().chain(extra_args).copied().collect();
+                    context.validate_args(format, &args)
+                }
+                None => Ok(()),
+            });
+            let Err(err) = result else {
+                continue;
+            };
+            let inherited = base_fields.as_ref().is_some_and(|base_fields| {
+                base_fields
+                    .iter()
+                    .any(|(base_key, base_format, _)| base_key == key && base_format == format)
+            });
+            match base {
+                Some(base) if inherited => anyhow::bail!(
+                    "{}: `{key}`: {err:#} (with the settings in {})",
+                    base.settings_file.display(),
+                    self.settings_file.display()
+                ),
+                _ => anyhow::bail!("{}: `{key}`: {err:#}", self.settings_file.display()),
+            }
+        }
+        Ok(())
     }
 
     pub fn subjects_dir(&self) -> &Path {
         &self.subjects_dir
     }
 
-    pub fn hw_dir(&self, num: usize) -> Result<String, formatx::Error> {
-        formatx!(self.hw_dir_format.to_owned(), num = num)
+    pub fn hw_dir_format(&self) -> &str {
+        &self.hw_dir_format
+    }
+
+    pub fn hw_dir(&self, num: usize) -> anyhow::Result<String> {
+        self.format_context()
+            .format(&self.hw_dir_format, &[("num", &num)])
+    }
+
+    /// Compile `hw_dir_format` into a matcher that extracts the HW number from a directory name.
+    pub fn hw_dir_matcher(&self) -> anyhow::Result<FormatMatcher> {
+        let matcher = FormatMatcher::new(&self.hw_dir_format, "num")?;
+        if !matcher.uses_arg() {
+            anyhow::bail!(
+                "hw_dir_format {:?} does not use `num`",
+                &*self.hw_dir_format
+            );
+        }
+        Ok(matcher)
     }
 
-    pub fn max_hw_dirs(&self) -> usize {
+    pub fn max_hw_dirs(&self) -> Option<usize> {
         self.max_hw_dirs
     }
 
@@ -97,11 +282,207 @@ impl Settings {
         self.hebrew_name.as_deref().unwrap_or("")
     }
 
+    /// The named arguments available to every format specifier:
+    /// the date and time arguments, `hebrew_name`, `week`, the fields of the profile, and the user-defined variables
+    /// (in increasing precedence).
+    pub fn format_context(&self) -> FormatContext {
+        let now = self.clock.now();
+        let week = self.semester.iter().map(|semester| {
+            (
+                "week".to_owned(),
+                semester.week(now.date_naive()).to_string(),
+            )
+        });
+        let variables = self
+            .variables
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()));
+
+        FormatContext::new(
+            now,
+            std::iter::once(("hebrew_name".to_owned(), self.hebrew_name().to_owned()))
+                .chain(week)
+                .chain(self.profile.args())
+                .chain(variables),
+        )
+    }
+
     pub fn questions_file_settings(&self) -> Option<&QuestionsFileSettings> {
         self.questions_file.as_ref()
     }
 
-    pub fn lyx_file_settings(&self) -> Option<&LyxFileSettings> {
-        self.lyx_file.as_ref()
+    pub fn template_dir_settings(&self) -> Option<&TemplateDirSettings> {
+        self.template_dir.as_ref()
+    }
+
+    /// The documents created in every new HW directory: the `lyx_file` (if any), then `documents`.
+    pub fn documents(&self) -> Box<[DocumentSettings]> {
+        self.lyx_file
+            .iter()
+            .map(LyxFileSettings::document_settings)
+            .chain(self.documents.iter().cloned())
+            .collect()
+    }
+
+    pub fn export_settings(&self) -> Option<&ExportSettings> {
+        self.export.as_ref()
+    }
+
+    pub fn submission_settings(&self) -> Option<&SubmissionSettings> {
+        self.submission.as_ref()
+    }
+
+    pub fn checks_settings(&self) -> Option<&ChecksSettings> {
+        self.checks.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
+    use chrono::{Local, TimeZone};
+
+    fn settings(extra: &str) -> Settings {
+        let json = format!(
+            r#"{{
+                "subjects_dir": "subjects",
+                "hw_dir_format": "HW{{num}}",
+                "open_after_creation": false,
+                "interface": {{ "type": "CMD", "subject_label_format": "{{name}}" }}
+                {extra}
+            }}"#
+        );
+        let settings: Settings = Config::builder()
+            .add_source(File::from_str(&json, FileFormat::Json))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+        let now = Local.with_ymd_and_hms(2026, 11, 2, 12, 0, 0).unwrap();
+        settings.with_clock(Clock::Fixed(now))
+    }
+
+    #[test]
+    fn variables_override_profile() {
+        let settings = settings(
+            r#", "profile": { "id": "123" }, "variables": { "student_id": "456", "course": "104031" }"#,
+        );
+        let context = settings.format_context();
+        assert_eq!(context.args()["student_id"], "456");
+        assert_eq!(context.args()["course"], "104031");
+        assert_eq!(
+            context.format("hw{num}_{course}", &[("num", &3)]).unwrap(),
+            "hw3_104031"
+        );
+    }
+
+    #[test]
+    fn fixed_clock() {
+        let context = settings("").format_context();
+        assert_eq!(context.args()["date"], "2026-11-02");
+        assert_eq!(context.args()["weekday"], "Monday");
+        assert_eq!(
+            context.format("{now:%d/%m %H:%M}", &[]).unwrap(),
+            "02/11 12:00"
+        );
+    }
+
+    #[test]
+    fn week_arg() {
+        assert!(!settings("").format_context().args().contains_key("week"));
+        let settings = settings(r#", "semester": { "start": "2026-10-18", "end": "2027-01-22" }"#);
+        assert_eq!(settings.format_context().args()["week"], "3");
+    }
+
+    /// Write global settings with the given `submission.filename_format` in `dir`,
+    /// along with subject settings files, by subject name.
+    fn settings_files(dir: &TempDir, filename_format: &str, subjects: &[(&str, &str)]) -> String {
+        let subjects_dir = dir.path().join("subjects");
+        for (subject, contents) in subjects {
+            dir.write(&format!("subjects/{subject}/s.json5"), contents);
+        }
+        let json = format!(
+            r#"{{
+                "subjects_dir": {subjects_dir:?},
+                "hw_dir_format": "HW{{num}}",
+                "open_after_creation": false,
+                "interface": {{ "type": "CMD", "subject_label_format": "{{name}}" }},
+                "subject_settings_filename": "s.json5",
+                "submission": {{ "filename_format": {filename_format:?}, "include": ["*.pdf"] }}
+            }}"#
+        );
+        dir.write("settings.json", &json).display().to_string()
+    }
+
+    #[test]
+    fn unknown_global_arg() {
+        let dir = TempDir::new("unknown_global_arg");
+        let settings_file = settings_files(&dir, "hw{nmu}.zip", &[("A", "{}")]);
+        let err = Settings::new(&settings_file).err().unwrap();
+        assert_eq!(
+            format!("{err:#}").split(" (").next().unwrap(),
+            format!("{settings_file}: `submission.filename_format`: unknown argument `nmu`")
+        );
+    }
+
+    #[test]
+    fn positional_global_arg() {
+        let dir = TempDir::new("positional_global_arg");
+        let settings_file = settings_files(&dir, "hw{}.zip", &[]);
+        assert!(Settings::new(&settings_file).is_err());
+    }
+
+    #[test]
+    fn arg_of_some_subjects() {
+        let dir = TempDir::new("arg_of_some_subjects");
+        let settings_file = settings_files(
+            &dir,
+            "hw{num}_{course}.zip",
+            &[
+                ("A", r#"{ variables: { course: "104031" } }"#),
+                ("B", r#"{ variables: { year_of_study: "2" } }"#),
+            ],
+        );
+        let settings = Settings::new(&settings_file).unwrap();
+
+        let subjects_dir = dir.path().join("subjects");
+        let a = settings.update(&subjects_dir.join("A/s.json5")).unwrap();
+        assert_eq!(
+            a.submission_settings()
+                .unwrap()
+                .filename(1, &a.format_context())
+                .unwrap(),
+            "hw1_104031.zip"
+        );
+
+        // the format specifier comes from the global settings, so the error names the global settings file
+        let b_file = subjects_dir.join("B/s.json5");
+        let err = settings.update(&b_file).err().unwrap();
+        let err = format!("{err:#}");
+        assert!(err.starts_with(&format!(
+            "{settings_file}: `submission.filename_format`: unknown argument `course`"
+        )));
+        assert!(err.ends_with(&format!("(with the settings in {})", b_file.display())));
+    }
+
+    #[test]
+    fn invalid_subject_format() {
+        let dir = TempDir::new("invalid_subject_format");
+        let settings_file = settings_files(
+            &dir,
+            "hw{num}.zip",
+            &[(
+                "A",
+                r#"{ submission: { filename_format: "hw{grade}.zip" } }"#,
+            )],
+        );
+        let settings = Settings::new(&settings_file).unwrap();
+        let a_file = dir.path().join("subjects/A/s.json5");
+        let err = settings.update(&a_file).err().unwrap();
+        assert!(format!("{err:#}").starts_with(&format!(
+            "{}: `submission.filename_format`: unknown argument `grade`",
+            a_file.display()
+        )));
     }
 }
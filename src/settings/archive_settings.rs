@@ -0,0 +1,45 @@
+use super::format_context::FormatContext;
+use super::format_string::{FormatFields, FormatString};
+use glob::Pattern;
+use serde::{Deserialize, Serialize};
+
+/// Settings for extracting a questions file that is an archive (`.zip`, `.tar.gz` or `.tgz`),
+/// instead of moving it into the HW directory as-is.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ArchiveSettings {
+    /// Subdirectory of the HW directory to extract into. If `None`, extract into the HW directory itself.
+    subdir_format: Option<FormatString>,
+    questions_glob: Box<str>,
+}
+
+impl ArchiveSettings {
+    /// The name of the subdirectory to extract an archive into, if any.
+    /// `stem` is the name of the archive without the archive extension (e.g. `hw1` for `hw1.tar.gz`).
+    pub fn subdir(
+        &self,
+        num: usize,
+        stem: &str,
+        context: &FormatContext,
+    ) -> anyhow::Result<Option<String>> {
+        self.subdir_format
+            .as_ref()
+            .map(|subdir_format| context.format(subdir_format, &[("num", &num), ("stem", &stem)]))
+            .transpose()
+    }
+
+    pub(super) fn format_fields<'a>(&'a self, key: &str, fields: &mut FormatFields<'a>) {
+        if let Some(subdir_format) = &self.subdir_format {
+            fields.add(
+                format_args!("{key}.subdir_format"),
+                subdir_format,
+                &["num", "stem"],
+            );
+        }
+    }
+
+    /// The pattern that locates the questions file among the paths inside the archive.
+    pub fn questions_pattern(&self) -> anyhow::Result<Pattern> {
+        Ok(Pattern::new(&self.questions_glob)?)
+    }
+}
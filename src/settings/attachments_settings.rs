@@ -0,0 +1,148 @@
+use super::format_context::FormatContext;
+use super::format_matcher::FormatMatcher;
+use super::format_string::{FormatFields, FormatString};
+use super::open_settings::{Formattable, OpenSettings};
+use super::questions_file_settings::name_part;
+use glob::Pattern;
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{self, Path},
+    time::Duration,
+};
+
+/// Settings for moving additional downloads (other than the questions file) into a new HW directory.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AttachmentsSettings {
+    count: Option<usize>,
+    within_minutes: Option<u64>,
+    #[serde(default)]
+    choose_interactively: bool,
+    #[serde(default)]
+    rules: Box<[AttachmentRule]>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AttachmentRule {
+    name_glob: Box<str>,
+    filename_format: FormatString,
+
+    open: Option<OpenSettings<Attachment>>,
+}
+
+impl AttachmentsSettings {
+    /// Maximum number of attachments.
+    pub fn count(&self) -> Option<usize> {
+        self.count
+    }
+
+    /// Only downloads created at most this long before the most recent download are attachments.
+    pub fn within(&self) -> Option<Duration> {
+        self.within_minutes
+            .map(|minutes| Duration::from_secs(minutes * 60))
+    }
+
+    pub fn choose_interactively(&self) -> bool {
+        self.choose_interactively
+    }
+
+    pub fn rules(&self) -> &[AttachmentRule] {
+        &self.rules
+    }
+
+    pub(super) fn format_fields<'a>(&'a self, key: &str, fields: &mut FormatFields<'a>) {
+        for (i, rule) in self.rules.iter().enumerate() {
+            let key = format!("{key}.rules[{i}]");
+            fields.add(
+                format_args!("{key}.filename_format"),
+                &rule.filename_format,
+                &["num", "index", "ext", "stem", "original_name"],
+            );
+            if let Some(open) = &rule.open {
+                open.format_fields(&format!("{key}.open"), fields);
+            }
+        }
+    }
+
+    /// The first rule whose glob matches the name of the download, if any.
+    pub fn rule_for(&self, download: &Path) -> anyhow::Result<Option<&AttachmentRule>> {
+        let name = name_part(download.file_name());
+        for rule in self.rules.iter() {
+            if rule.matches_name(&name)? {
+                return Ok(Some(rule));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl AttachmentRule {
+    /// Whether `name_glob` matches the filename `name`.
+    pub fn matches_name(&self, name: &str) -> anyhow::Result<bool> {
+        Ok(Pattern::new(&self.name_glob)?.matches(name))
+    }
+
+    /// The filename of an attachment moved from `download`.
+    /// `index` is the (1-based) index of the attachment among the attachments of the HW.
+    pub fn attachment_filename(
+        &self,
+        num: usize,
+        index: usize,
+        download: &Path,
+        context: &FormatContext,
+    ) -> anyhow::Result<String> {
+        context.format(
+            &self.filename_format,
+            &[
+                ("num", &num),
+                ("index", &index),
+                ("ext", &name_part(download.extension())),
+                ("stem", &name_part(download.file_stem())),
+                ("original_name", &name_part(download.file_name())),
+            ],
+        )
+    }
+
+    /// Whether `filename` may be the name of an attachment of HW `num` that this rule was applied to.
+    ///
+    /// The glob matched the name of the download, so it is checked against that name when `filename_format` keeps it
+    /// (with `original_name`, or with `stem` and `ext`).
+    pub fn matches_attachment(&self, filename: &str, num: usize) -> anyhow::Result<bool> {
+        let matcher = self.attachment_filename_matcher()?;
+        let Some(args) = matcher.match_args(filename, num) else {
+            return Ok(false);
+        };
+        let original_name = match (args.get("original_name"), args.get("stem"), args.get("ext")) {
+            (Some(original_name), _, _) => original_name.to_string(),
+            (None, Some(stem), Some(&"")) => stem.to_string(),
+            (None, Some(stem), Some(ext)) => format!("{stem}.{ext}"),
+            _ => return Ok(true),
+        };
+        self.matches_name(&original_name)
+    }
+
+    /// Compile `filename_format` into a matcher that recognizes filenames of attachments.
+    pub fn attachment_filename_matcher(&self) -> anyhow::Result<FormatMatcher> {
+        FormatMatcher::new(&self.filename_format, "num")
+    }
+
+    pub fn open_settings(&self) -> Option<&OpenSettings<Attachment>> {
+        self.open.as_ref()
+    }
+}
+
+pub struct Attachment;
+impl Formattable for Attachment {
+    type Params = Path;
+    const ARGS: &'static [&'static str] = &["attachment"];
+
+    fn format(s: &str, attachment: &Path, context: &FormatContext) -> anyhow::Result<String> {
+        let absolute_attachment = path::absolute(attachment)?;
+        let attachment = absolute_attachment
+            .to_str()
+            .ok_or(anyhow::anyhow!("cannot convert attachment to string"))?;
+
+        context.format(s, &[("attachment", &attachment)])
+    }
+}
@@ -0,0 +1,41 @@
+use glob::Pattern;
+use serde::{Deserialize, Serialize};
+
+/// Settings for the checks that are run on a HW before submitting it.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChecksSettings {
+    #[serde(default)]
+    export_up_to_date: bool,
+    #[serde(default)]
+    no_placeholders: bool,
+    #[serde(default)]
+    required_files: Box<[Box<str>]>,
+    max_size_kb: Option<u64>,
+}
+
+impl ChecksSettings {
+    /// Whether to check that the exported file exists, and is newer than the LyX file.
+    pub fn export_up_to_date(&self) -> bool {
+        self.export_up_to_date
+    }
+
+    /// Whether to check that no `from` string of the documents' replacements remains in the documents
+    /// (outside of the text it was replaced with, and except for replacements with a `count`).
+    pub fn no_placeholders(&self) -> bool {
+        self.no_placeholders
+    }
+
+    /// Glob patterns (relative to the HW directory) that have to match at least one file each.
+    pub fn required_files(&self) -> anyhow::Result<Vec<Pattern>> {
+        self.required_files
+            .iter()
+            .map(|required_file| Ok(Pattern::new(required_file)?))
+            .collect()
+    }
+
+    /// Maximum size of every submitted file.
+    pub fn max_size_kb(&self) -> Option<u64> {
+        self.max_size_kb
+    }
+}
@@ -0,0 +1,218 @@
+use super::format_context::FormatContext;
+use super::format_matcher::FormatMatcher;
+use super::format_string::{FormatFields, FormatString};
+use super::lyx_edit_settings::LyxEditSettings;
+use super::open_settings::{Formattable, OpenSettings};
+use anyhow::anyhow;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::{self, Path};
+
+/// Settings for a document (e.g. a LyX, LaTeX, Markdown or Typst file) created in every new HW directory.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DocumentSettings {
+    template_file: Option<Box<Path>>,
+    filename_format: FormatString,
+    #[serde(default)]
+    replacements: Box<[ReplacementConfig]>,
+    #[serde(default)]
+    engine: DocumentEngine,
+    #[serde(default)]
+    fields: serde_json::Map<String, serde_json::Value>,
+    lyx: Option<LyxEditSettings>,
+
+    open: Option<OpenSettings<Document>>,
+}
+
+/// How the document is generated from its template.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentEngine {
+    /// Copy the template, applying the `replacements`.
+    #[default]
+    Replacements,
+    /// Render the template with the Jinja template engine, then apply the `replacements`.
+    Jinja,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReplacementConfig {
+    from: Box<str>,
+    to_format: FormatString,
+    count: Option<usize>,
+    #[serde(default)]
+    mode: ReplacementMode,
+    #[serde(default)]
+    whole_word: bool,
+    #[serde(default)]
+    anchor: ReplacementAnchor,
+    #[serde(default)]
+    required: bool,
+}
+
+/// How `from` is interpreted.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplacementMode {
+    #[default]
+    Literal,
+    /// `from` is a regular expression, and `to_format` may refer to its capture groups (e.g. `$1`).
+    Regex,
+}
+
+/// Where in a line `from` has to appear.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplacementAnchor {
+    #[default]
+    None,
+    LineStart,
+    LineEnd,
+    /// `from` has to be a whole line.
+    Line,
+}
+
+impl DocumentSettings {
+    pub(super) fn new(
+        template_file: Option<Box<Path>>,
+        filename_format: FormatString,
+        replacements: Box<[ReplacementConfig]>,
+        open: Option<OpenSettings<Document>>,
+    ) -> Self {
+        Self {
+            template_file,
+            filename_format,
+            replacements,
+            engine: DocumentEngine::default(),
+            fields: serde_json::Map::new(),
+            lyx: None,
+            open,
+        }
+    }
+
+    pub(super) fn format_fields<'a>(&'a self, key: &str, fields: &mut FormatFields<'a>) {
+        fields.add(
+            format_args!("{key}.filename_format"),
+            &self.filename_format,
+            &["num"],
+        );
+        ReplacementConfig::format_fields(&self.replacements, key, fields);
+        if let Some(lyx) = &self.lyx {
+            lyx.format_fields(&format!("{key}.lyx"), fields);
+        }
+        if let Some(open) = &self.open {
+            open.format_fields(&format!("{key}.open"), fields);
+        }
+    }
+
+    pub fn template_file(&self) -> Option<&Path> {
+        self.template_file.as_deref()
+    }
+
+    pub fn filename(&self, num: usize, context: &FormatContext) -> anyhow::Result<String> {
+        context.format(&self.filename_format, &[("num", &num)])
+    }
+
+    /// Compile `filename_format` into a matcher that recognizes the document's filename,
+    /// whatever the date was when it was created.
+    pub fn filename_matcher(&self) -> anyhow::Result<FormatMatcher> {
+        FormatMatcher::new(&self.filename_format, "num")
+    }
+
+    pub fn replacements(&self) -> &[ReplacementConfig] {
+        &self.replacements
+    }
+
+    pub fn engine(&self) -> DocumentEngine {
+        self.engine
+    }
+
+    /// Custom variables for the template engine.
+    pub fn fields(&self) -> &serde_json::Map<String, serde_json::Value> {
+        &self.fields
+    }
+
+    /// Structure-aware edits, for LyX documents.
+    pub fn lyx_edit_settings(&self) -> Option<&LyxEditSettings> {
+        self.lyx.as_ref()
+    }
+
+    pub fn open_settings(&self) -> Option<&OpenSettings<Document>> {
+        self.open.as_ref()
+    }
+}
+
+impl ReplacementConfig {
+    /// Add the format specifiers of the `replacements` (of the settings at `key`).
+    pub(super) fn format_fields<'a>(
+        replacements: &'a [Self],
+        key: &str,
+        fields: &mut FormatFields<'a>,
+    ) {
+        for (i, replacement) in replacements.iter().enumerate() {
+            fields.add(
+                format_args!("{key}.replacements[{i}].to_format"),
+                &replacement.to_format,
+                &["num"],
+            );
+        }
+    }
+
+    pub fn from(&self) -> &str {
+        &self.from
+    }
+
+    pub fn to(&self, num: usize, context: &FormatContext) -> anyhow::Result<String> {
+        context.format(&self.to_format, &[("num", &num)])
+    }
+
+    pub fn count(&self) -> Option<usize> {
+        self.count
+    }
+
+    /// Whether the replacement string may refer to capture groups.
+    pub fn expands_captures(&self) -> bool {
+        self.mode == ReplacementMode::Regex
+    }
+
+    /// Whether a template in which `from` is not found is an error.
+    pub fn required(&self) -> bool {
+        self.required
+    }
+
+    /// Compile `from` (along with the whole-word and anchor modes) into a regular expression.
+    pub fn regex(&self) -> anyhow::Result<Regex> {
+        let mut pattern = match self.mode {
+            ReplacementMode::Literal => regex::escape(&self.from),
+            ReplacementMode::Regex => format!("(?:{})", self.from),
+        };
+        if self.whole_word {
+            pattern = format!(r"\b{pattern}\b");
+        }
+        pattern = match self.anchor {
+            ReplacementAnchor::None => pattern,
+            ReplacementAnchor::LineStart => format!("(?m:^){pattern}"),
+            ReplacementAnchor::LineEnd => format!("{pattern}(?m:$)"),
+            ReplacementAnchor::Line => format!("(?m:^){pattern}(?m:$)"),
+        };
+        Ok(Regex::new(&pattern)?)
+    }
+}
+
+pub struct Document;
+impl Formattable for Document {
+    type Params = Path;
+    const ARGS: &'static [&'static str] = &["document", "lyx_file"];
+
+    fn format(s: &str, document: &Path, context: &FormatContext) -> anyhow::Result<String> {
+        let absolute_document = path::absolute(document)?;
+        let document = absolute_document
+            .to_str()
+            .ok_or(anyhow!("cannot convert document to string"))?;
+
+        // `lyx_file` is the name of the argument in the (older) `lyx_file` settings
+        context.format(s, &[("document", &document), ("lyx_file", &document)])
+    }
+}
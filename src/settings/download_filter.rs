@@ -0,0 +1,160 @@
+use glob::Pattern;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::Metadata,
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+/// Extensions of files that are still being downloaded.
+const INCOMPLETE_DOWNLOAD_EXTENSIONS: [&str; 5] =
+    ["crdownload", "part", "partial", "download", "tmp"];
+
+/// Rules for which files in the downloads directory can be used as the questions file.
+///
+/// Directories, hidden files and incomplete downloads are always skipped.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DownloadFilter {
+    extensions: Option<Box<[Box<str>]>>,
+    name_glob: Option<Box<str>>,
+    name_regex: Option<Box<str>>,
+    max_age_minutes: Option<u64>,
+    min_size: Option<u64>,
+}
+
+impl DownloadFilter {
+    /// Compile the filter into a predicate on a file and its metadata.
+    pub fn matcher(&self) -> anyhow::Result<impl Fn(&Path, &Metadata) -> bool + '_> {
+        let name_glob = self.name_glob.as_deref().map(Pattern::new).transpose()?;
+        let name_regex = self.name_regex.as_deref().map(Regex::new).transpose()?;
+        let max_age = self
+            .max_age_minutes
+            .map(|minutes| Duration::from_secs(minutes * 60));
+
+        Ok(move |path: &Path, meta: &Metadata| {
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                return false;
+            };
+            let extension = path
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .unwrap_or("");
+
+            if !meta.is_file()
+                || name.starts_with('.')
+                || INCOMPLETE_DOWNLOAD_EXTENSIONS
+                    .iter()
+                    .any(|incomplete| extension.eq_ignore_ascii_case(incomplete))
+            {
+                return false;
+            }
+
+            if let Some(extensions) = &self.extensions {
+                if !extensions.iter().any(|allowed| {
+                    allowed
+                        .trim_start_matches('.')
+                        .eq_ignore_ascii_case(extension)
+                }) {
+                    return false;
+                }
+            }
+            if name_glob.as_ref().is_some_and(|glob| !glob.matches(name)) {
+                return false;
+            }
+            if name_regex
+                .as_ref()
+                .is_some_and(|regex| !regex.is_match(name))
+            {
+                return false;
+            }
+            if let Some(max_age) = max_age {
+                let age = meta
+                    .created()
+                    .ok()
+                    .and_then(|created| SystemTime::now().duration_since(created).ok());
+                if !age.is_some_and(|age| age <= max_age) {
+                    return false;
+                }
+            }
+            if self.min_size.is_some_and(|min_size| meta.len() < min_size) {
+                return false;
+            }
+
+            true
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    /// The names of the files in `dir` that pass the filter given as JSON.
+    fn passing(dir: &TempDir, filter: &str) -> Vec<String> {
+        let filter: DownloadFilter = serde_json::from_str(filter).unwrap();
+        let matcher = filter.matcher().unwrap();
+        let mut names: Vec<_> = dir
+            .path()
+            .read_dir()
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| matcher(path, &path.metadata().unwrap()))
+            .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    fn downloads(name: &str) -> TempDir {
+        let dir = TempDir::new(name);
+        dir.write("hw1.pdf", "questions");
+        dir.write("HW2.PDF", "");
+        dir.write("skel.zip", "files");
+        dir.write("notes.txt", "some notes");
+        dir.write(".hidden.pdf", "questions");
+        dir.write("hw3.pdf.crdownload", "questions");
+        dir.write("dir.pdf/file", "");
+        dir
+    }
+
+    #[test]
+    fn always_skipped() {
+        let dir = downloads("filter_always_skipped");
+        assert_eq!(
+            passing(&dir, "{}"),
+            ["HW2.PDF", "hw1.pdf", "notes.txt", "skel.zip"]
+        );
+    }
+
+    #[test]
+    fn extensions() {
+        let dir = downloads("filter_extensions");
+        assert_eq!(
+            passing(&dir, r#"{ "extensions": ["pdf", ".zip"] }"#),
+            ["HW2.PDF", "hw1.pdf", "skel.zip"]
+        );
+    }
+
+    #[test]
+    fn names() {
+        let dir = downloads("filter_names");
+        assert_eq!(passing(&dir, r#"{ "name_glob": "hw*" }"#), ["hw1.pdf"]);
+        assert_eq!(
+            passing(&dir, r#"{ "name_regex": "(?i)^hw\\d" }"#),
+            ["HW2.PDF", "hw1.pdf"]
+        );
+    }
+
+    #[test]
+    fn size_and_age() {
+        let dir = downloads("filter_size_and_age");
+        assert_eq!(
+            passing(&dir, r#"{ "min_size": 6 }"#),
+            ["hw1.pdf", "notes.txt"]
+        );
+        assert_eq!(passing(&dir, r#"{ "max_age_minutes": 5 }"#).len(), 4);
+    }
+}
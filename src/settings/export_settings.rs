@@ -0,0 +1,62 @@
+use super::format_context::FormatContext;
+use super::format_string::{FormatFields, FormatString};
+use super::open_settings::{Formattable, OpenSettings};
+use serde::{Deserialize, Serialize};
+use std::path::{self, PathBuf};
+
+/// Settings for exporting the LyX file of a HW to PDF (e.g. using `lyx --export-to`).
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExportSettings {
+    command: OpenSettings<Export>,
+    submission_filename_format: FormatString,
+}
+
+impl ExportSettings {
+    pub fn command(&self) -> &OpenSettings<Export> {
+        &self.command
+    }
+
+    pub(super) fn format_fields<'a>(&'a self, key: &str, fields: &mut FormatFields<'a>) {
+        self.command
+            .format_fields(&format!("{key}.command"), fields);
+        fields.add(
+            format_args!("{key}.submission_filename_format"),
+            &self.submission_filename_format,
+            &["num"],
+        );
+    }
+
+    pub fn submission_filename(
+        &self,
+        num: usize,
+        context: &FormatContext,
+    ) -> anyhow::Result<String> {
+        context.format(&self.submission_filename_format, &[("num", &num)])
+    }
+}
+
+/// The files involved in an export.
+pub struct ExportFiles {
+    pub lyx_file: PathBuf,
+    pub output: PathBuf,
+}
+
+pub struct Export;
+impl Formattable for Export {
+    type Params = ExportFiles;
+    const ARGS: &'static [&'static str] = &["lyx_file", "output"];
+
+    fn format(s: &str, files: &ExportFiles, context: &FormatContext) -> anyhow::Result<String> {
+        let absolute_lyx_file = path::absolute(&files.lyx_file)?;
+        let lyx_file = absolute_lyx_file
+            .to_str()
+            .ok_or(anyhow::anyhow!("cannot convert LyX file to string"))?;
+        let absolute_output = path::absolute(&files.output)?;
+        let output = absolute_output
+            .to_str()
+            .ok_or(anyhow::anyhow!("cannot convert output file to string"))?;
+
+        context.format(s, &[("lyx_file", &lyx_file), ("output", &output)])
+    }
+}
@@ -0,0 +1,201 @@
+use super::format_matcher::{parse_format, Segment};
+use chrono::{
+    format::{Item, StrftimeItems},
+    DateTime, Datelike, Local,
+};
+use formatx::Template;
+use std::{
+    collections::BTreeMap,
+    fmt::{Debug, Display},
+};
+
+/// A value of a named argument of a format specifier.
+pub trait FormatArg: Display + Debug {}
+impl<T: Display + Debug + ?Sized> FormatArg for T {}
+
+/// The strftime format of `{now}`, when no format is given.
+const DEFAULT_NOW_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// The named arguments that are available to every format specifier:
+/// the date and time arguments, `hebrew_name`, `week` (if there is a `semester`), the fields of the `profile`,
+/// and the user-defined `variables`.
+pub struct FormatContext {
+    now: DateTime<Local>,
+    args: BTreeMap<String, String>,
+}
+
+impl FormatContext {
+    /// Build a context from arguments, along with the date and time arguments of `now`.
+    /// Later arguments override earlier ones with the same name.
+    pub fn new(now: DateTime<Local>, args: impl IntoIterator<Item = (String, String)>) -> Self {
+        let date_args = [
+            ("date", now.format("%Y-%m-%d").to_string()),
+            ("year", now.year().to_string()),
+            ("month", now.month().to_string()),
+            ("day", now.day().to_string()),
+            ("weekday", now.format("%A").to_string()),
+        ]
+        .map(|(name, value)| (name.to_owned(), value));
+
+        Self {
+            now,
+            args: date_args.into_iter().chain(args).collect(),
+        }
+    }
+
+    /// The time used for the date and time arguments.
+    pub fn now(&self) -> DateTime<Local> {
+        self.now
+    }
+
+    /// The arguments of the context, by name.
+    pub fn args(&self) -> &BTreeMap<String, String> {
+        &self.args
+    }
+
+    /// Format `format` with the arguments specific to it (e.g. `num`) and the arguments of the context.
+    /// The specific arguments take precedence.
+    ///
+    /// `{now:<strftime format>}` (e.g. `{now:%d/%m}`) formats the current time.
+    pub fn format(&self, format: &str, args: &[(&str, &dyn FormatArg)]) -> anyhow::Result<String> {
+        let mut template = Template::new(self.expand_now(format)?)?;
+        for (name, value) in args {
+            template.replace(name, value);
+        }
+        for (name, value) in &self.args {
+            template.replace(name, value);
+        }
+        Ok(template.text()?)
+    }
+
+    /// Check that `format` is a valid format specifier that only uses named arguments.
+    pub fn validate(&self, format: &str) -> anyhow::Result<()> {
+        for segment in parse_format(format)? {
+            let Segment::Placeholder { name, .. } = segment else {
+                continue;
+            };
+            if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+                anyhow::bail!(
+                    "positional arguments are not supported (use a named argument instead)"
+                );
+            }
+        }
+        Template::new(self.expand_now(format)?)?;
+        Ok(())
+    }
+
+    /// Check that `format` only uses the named arguments `args` (the arguments specific to it), `now`,
+    /// and the arguments of the context.
+    pub fn validate_args(&self, format: &str, args: &[&str]) -> anyhow::Result<()> {
+        for segment in parse_format(format)? {
+            let Segment::Placeholder { name, .. } = segment else {
+                continue;
+            };
+            if name != "now" && !args.contains(&name) && !self.args.contains_key(name) {
+                let available = args
+                    .iter()
+                    .copied()
+                    .chain(std::iter::once("now"))
+                    .chain(self.args.keys().map(String::as_str))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                anyhow::bail!("unknown argument `{name}` (the arguments are: {available})");
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace the `{now:...}` placeholders, whose strftime formats `formatx` cannot parse.
+    fn expand_now(&self, format: &str) -> anyhow::Result<String> {
+        let mut expanded = String::new();
+        for segment in parse_format(format)? {
+            match segment {
+                Segment::Literal(literal) => expanded.push_str(&escape_braces(&literal)),
+                Segment::Placeholder { name: "now", text } => {
+                    let now_format = text.split_once(':').map_or(DEFAULT_NOW_FORMAT, |(_, f)| f);
+                    let items: Vec<_> = StrftimeItems::new(now_format).collect();
+                    if items.contains(&Item::Error) {
+                        anyhow::bail!("invalid strftime format {now_format:?} in {format:?}");
+                    }
+                    let now = self.now.format_with_items(items.into_iter()).to_string();
+                    expanded.push_str(&escape_braces(&now));
+                }
+                Segment::Placeholder { text, .. } => {
+                    expanded.push('{');
+                    expanded.push_str(text);
+                    expanded.push('}');
+                }
+            }
+        }
+        Ok(expanded)
+    }
+}
+
+fn escape_braces(s: &str) -> String {
+    s.replace('{', "{{").replace('}', "}}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::Clock;
+    use chrono::TimeZone;
+
+    fn context() -> FormatContext {
+        let now = Local.with_ymd_and_hms(2026, 3, 1, 9, 5, 0).unwrap();
+        FormatContext::new(
+            Clock::Fixed(now).now(),
+            [("course".to_owned(), "104031".to_owned())],
+        )
+    }
+
+    #[test]
+    fn specific_args_take_precedence() {
+        let context = context();
+        assert_eq!(
+            context
+                .format("hw{num}_{course}", &[("num", &3), ("course", &"x")])
+                .unwrap(),
+            "hw3_x"
+        );
+        assert_eq!(context.format("{{{course}}}", &[]).unwrap(), "{104031}");
+    }
+
+    #[test]
+    fn date_args() {
+        let context = context();
+        assert_eq!(
+            context
+                .format("{date} {year}/{month:02}/{day} {weekday}", &[])
+                .unwrap(),
+            "2026-03-01 2026/03/1 Sunday"
+        );
+    }
+
+    #[test]
+    fn now_arg() {
+        let context = context();
+        assert_eq!(context.format("{now}", &[]).unwrap(), "2026-03-01 09:05");
+        assert_eq!(context.format("{now:%d/%m}", &[]).unwrap(), "01/03");
+        assert!(context.format("{now:%Q}", &[]).is_err());
+    }
+
+    #[test]
+    fn validate() {
+        let context = context();
+        assert!(context.validate("hw{num:02}_{now:%Y}").is_ok());
+        assert!(context.validate("hw{}").is_err());
+        assert!(context.validate("hw{0}").is_err());
+        assert!(context.validate("hw{num").is_err());
+    }
+
+    #[test]
+    fn validate_args() {
+        let context = context();
+        assert!(context
+            .validate_args("hw{num}_{course}_{year}_{now}", &["num"])
+            .is_ok());
+        let err = context.validate_args("hw{nmu}", &["num"]).unwrap_err();
+        assert!(err.to_string().contains("unknown argument `nmu`"));
+    }
+}
@@ -0,0 +1,257 @@
+use regex::Regex;
+use std::collections::BTreeMap;
+
+/// A part of a format specifier.
+pub enum Segment<'a> {
+    Literal(String),
+    /// A placeholder, e.g. `{num:02}`.
+    /// `text` is the part between the curly braces, and `name` is the argument's name.
+    Placeholder {
+        name: &'a str,
+        text: &'a str,
+    },
+}
+
+/// Split a format specifier into literal text and placeholders.
+pub fn parse_format(format: &str) -> anyhow::Result<Vec<Segment<'_>>> {
+    let mut segments = Vec::new();
+    let mut literal = String::new();
+    let mut rest = format;
+
+    while let Some(i) = rest.find(['{', '}']) {
+        literal.push_str(&rest[..i]);
+        let brace = rest.as_bytes()[i];
+        rest = &rest[i + 1..];
+
+        if rest.as_bytes().first() == Some(&brace) {
+            // escaped brace: `{{` or `}}`
+            literal.push(brace.into());
+            rest = &rest[1..];
+            continue;
+        }
+        if brace == b'}' {
+            anyhow::bail!("unmatched `}}` in format specifier {format:?}");
+        }
+
+        let end = rest
+            .find('}')
+            .ok_or_else(|| anyhow::anyhow!("unmatched `{{` in format specifier {format:?}"))?;
+        let text = &rest[..end];
+        let name = text.split(':').next().unwrap_or_default().trim();
+        rest = &rest[end + 1..];
+
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(std::mem::take(&mut literal)));
+        }
+        segments.push(Segment::Placeholder { name, text });
+    }
+    literal.push_str(rest);
+    if !literal.is_empty() {
+        segments.push(Segment::Literal(literal));
+    }
+
+    Ok(segments)
+}
+
+/// Extracts the value of a numeric argument from strings produced by a format specifier.
+///
+/// Placeholders of other arguments match anything.
+pub struct FormatMatcher {
+    format: String,
+    regex: Regex,
+    /// The text of each capture group in `regex`, e.g. `num:02`.
+    placeholders: Box<[String]>,
+    arg: &'static str,
+}
+
+impl FormatMatcher {
+    pub fn new(format: &str, arg: &'static str) -> anyhow::Result<Self> {
+        let mut pattern = String::from("^");
+        let mut placeholders = Vec::new();
+        for segment in parse_format(format)? {
+            match segment {
+                Segment::Literal(literal) => pattern.push_str(&regex::escape(&literal)),
+                Segment::Placeholder { name, text } if name == arg => {
+                    // the number is made of digits, so the other placeholders cannot swallow it,
+                    // except for padding with a fill character (e.g. `{num:_>3}`)
+                    let spec = text.split_once(':').map_or("", |(_name, spec)| spec);
+                    if spec.contains(['<', '^', '>']) {
+                        pattern.push_str(r"(\D*[+-]?\d+\D*)");
+                    } else {
+                        pattern.push_str(r"([+-]?\d+)");
+                    }
+                    placeholders.push(text.to_owned());
+                }
+                Segment::Placeholder { .. } => pattern.push_str(".*"),
+            }
+        }
+        pattern.push('$');
+
+        Ok(Self {
+            format: format.to_owned(),
+            regex: Regex::new(&pattern)?,
+            placeholders: placeholders.into(),
+            arg,
+        })
+    }
+
+    /// Whether the format specifier uses the argument at all.
+    pub fn uses_arg(&self) -> bool {
+        !self.placeholders.is_empty()
+    }
+
+    /// Get the value of the argument that formats into `s`, if any.
+    ///
+    /// When the other placeholders could end in digits too (e.g. `{stem}_{num}` and `a_1_2`),
+    /// the longest match of the other placeholders wins.
+    pub fn match_num(&self, s: &str) -> Option<usize> {
+        let captures = self.regex.captures(s)?;
+
+        let mut num = None;
+        for (text, capture) in self.placeholders.iter().zip(captures.iter().skip(1)) {
+            let capture = capture?.as_str();
+
+            // strip padding, then make sure formatting the number gives back exactly the same text
+            let n: usize = capture
+                .trim_matches(|c: char| !c.is_ascii_digit())
+                .parse()
+                .ok()?;
+            if self.format_num(text, n)? != capture || num.is_some_and(|num| num != n) {
+                return None;
+            }
+            num = Some(n);
+        }
+        num
+    }
+
+    /// Check whether `s` could have been formatted with the argument set to `num`.
+    pub fn matches(&self, s: &str, num: usize) -> bool {
+        self.match_args(s, num).is_some()
+    }
+
+    /// If `s` could have been formatted with the argument set to `num`, get the values of the other arguments
+    /// (the first of them when an argument appears more than once).
+    pub fn match_args<'a>(&'a self, s: &'a str, num: usize) -> Option<BTreeMap<&'a str, &'a str>> {
+        // with the argument's text known, any split of `s` between the other placeholders is tried
+        let mut pattern = String::from("^");
+        let mut names = Vec::new();
+        for segment in parse_format(&self.format).ok()? {
+            match segment {
+                Segment::Literal(literal) => pattern.push_str(&regex::escape(&literal)),
+                Segment::Placeholder { name, text } if name == self.arg => {
+                    pattern.push_str(&regex::escape(&self.format_num(text, num)?));
+                }
+                Segment::Placeholder { name, .. } => {
+                    pattern.push_str("(.*)");
+                    names.push(name);
+                }
+            }
+        }
+        pattern.push('$');
+
+        let captures = Regex::new(&pattern).ok()?.captures(s)?;
+        let mut args = BTreeMap::new();
+        for (name, capture) in names.into_iter().zip(captures.iter().skip(1)) {
+            args.entry(name).or_insert(capture?.as_str());
+        }
+        Some(args)
+    }
+
+    fn format_num(&self, text: &str, n: usize) -> Option<String> {
+        let mut template = formatx::Template::new(format!("{{{text}}}")).ok()?;
+        template.replace(self.arg, n);
+        template.text().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn match_num_plain() {
+        let matcher = FormatMatcher::new("HW{num}", "num").unwrap();
+        assert!(matcher.uses_arg());
+        assert_eq!(matcher.match_num("HW7"), Some(7));
+        assert_eq!(matcher.match_num("HW12"), Some(12));
+        assert_eq!(matcher.match_num("HW"), None);
+        assert_eq!(matcher.match_num("HW7x"), None);
+        assert_eq!(matcher.match_num("Ex7"), None);
+    }
+
+    #[test]
+    fn match_num_zero_padding() {
+        let matcher = FormatMatcher::new("HW{num:02}", "num").unwrap();
+        assert_eq!(matcher.match_num("HW07"), Some(7));
+        assert_eq!(matcher.match_num("HW123"), Some(123));
+        // formatting 7 with `{num:02}` does not give back `7`
+        assert_eq!(matcher.match_num("HW7"), None);
+        assert_eq!(matcher.match_num("HW007"), None);
+    }
+
+    #[test]
+    fn match_num_round_trip() {
+        let matcher = FormatMatcher::new("HW{num}_w{week}", "num").unwrap();
+        for num in [1, 9, 10, 150] {
+            assert_eq!(matcher.match_num(&format!("HW{num}_w4")), Some(num));
+        }
+        // leading zeros do not round-trip without padding
+        assert_eq!(matcher.match_num("HW01_w4"), None);
+    }
+
+    #[test]
+    fn match_num_repeated_placeholder() {
+        let matcher = FormatMatcher::new("{num}-{num}", "num").unwrap();
+        assert_eq!(matcher.match_num("3-3"), Some(3));
+        assert_eq!(matcher.match_num("3-4"), None);
+    }
+
+    #[test]
+    fn matches_num() {
+        let matcher = FormatMatcher::new("{stem}_{num}.{ext}", "num").unwrap();
+        assert!(matcher.matches("skel_1.py", 1));
+        assert!(!matcher.matches("skel_1.py", 2));
+        assert!(!matcher.matches("skel.py", 1));
+    }
+
+    #[test]
+    fn stem_with_separator() {
+        let matcher = FormatMatcher::new("{stem}_{num}.{ext}", "num").unwrap();
+        assert_eq!(matcher.match_num("my_skel_1.py"), Some(1));
+        assert_eq!(matcher.match_num("hw_2_3.tar.gz"), Some(3));
+        assert!(matcher.matches("my_skel_1.py", 1));
+        assert!(matcher.matches("hw_2_3.tar.gz", 3));
+        assert!(!matcher.matches("hw_2_3.tar.gz", 2));
+        assert!(!matcher.matches("my_skel_1.py", 2));
+    }
+
+    #[test]
+    fn match_args() {
+        let matcher = FormatMatcher::new("{stem}_{num}.{ext}", "num").unwrap();
+        let args = matcher.match_args("my_skel_1.py", 1).unwrap();
+        assert_eq!(args["stem"], "my_skel");
+        assert_eq!(args["ext"], "py");
+        assert_eq!(matcher.match_args("my_skel_1.py", 2), None);
+    }
+
+    #[test]
+    fn match_num_fill() {
+        let matcher = FormatMatcher::new("HW{num:_>3}", "num").unwrap();
+        assert_eq!(matcher.match_num("HW__7"), Some(7));
+        assert!(matcher.matches("HW_12", 12));
+    }
+
+    #[test]
+    fn without_arg() {
+        let matcher = FormatMatcher::new("{original_name}", "num").unwrap();
+        assert!(!matcher.uses_arg());
+        assert_eq!(matcher.match_num("anything"), None);
+    }
+
+    #[test]
+    fn unmatched_braces() {
+        assert!(parse_format("HW{num").is_err());
+        assert!(parse_format("HW}").is_err());
+        assert!(parse_format("{{HW}}").is_ok());
+    }
+}
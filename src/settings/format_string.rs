@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+use std::{fmt::Display, ops::Deref};
+
+/// A format specifier in the settings (a value whose key ends with `_format`).
+///
+/// Format specifiers are checked when the settings are loaded (see [`FormatFields`]),
+/// rather than when they are formatted.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct FormatString(Box<str>);
+
+impl Deref for FormatString {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+/// The format specifiers in the settings, each with its settings key and the names of its own arguments.
+#[derive(Default)]
+pub struct FormatFields<'a> {
+    fields: Vec<(String, &'a FormatString, &'static [&'static str])>,
+}
+
+impl<'a> FormatFields<'a> {
+    pub fn add(
+        &mut self,
+        key: impl Display,
+        format: &'a FormatString,
+        args: &'static [&'static str],
+    ) {
+        self.fields.push((key.to_string(), format, args));
+    }
+
+    pub fn add_all(
+        &mut self,
+        key: impl Display,
+        formats: &'a [FormatString],
+        args: &'static [&'static str],
+    ) {
+        for (i, format) in formats.iter().enumerate() {
+            self.add(format_args!("{key}[{i}]"), format, args);
+        }
+    }
+
+    pub fn iter(
+        &self,
+    ) -> impl Iterator<Item = &(String, &'a FormatString, &'static [&'static str])> {
+        self.fields.iter()
+    }
+}
@@ -1,6 +1,7 @@
+use super::format_context::FormatContext;
+use super::format_string::{FormatFields, FormatString};
 use crate::interface::InterfaceType;
 use crate::subject::Subject;
-use formatx::formatx;
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -8,7 +9,7 @@ use serde::{Deserialize, Serialize};
 pub struct InterfaceSettings {
     #[serde(rename = "type")]
     interface_type: InterfaceType,
-    subject_label_format: Box<str>,
+    subject_label_format: FormatString,
 }
 
 impl InterfaceSettings {
@@ -16,11 +17,25 @@ impl InterfaceSettings {
         &self.interface_type
     }
 
-    pub fn subject_label(&self, subject: &Subject) -> Result<String, formatx::Error> {
-        formatx!(
-            self.subject_label_format.to_owned(),
-            name = subject.name(),
-            num = subject.current_hw_num()
+    pub(super) fn format_fields<'a>(&'a self, key: &str, fields: &mut FormatFields<'a>) {
+        fields.add(
+            format_args!("{key}.subject_label_format"),
+            &self.subject_label_format,
+            &["name", "num"],
+        );
+    }
+
+    pub fn subject_label(
+        &self,
+        subject: &Subject,
+        context: &FormatContext,
+    ) -> anyhow::Result<String> {
+        context.format(
+            &self.subject_label_format,
+            &[
+                ("name", &subject.name()),
+                ("num", &subject.current_hw_num()),
+            ],
         )
     }
 }
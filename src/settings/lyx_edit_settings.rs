@@ -0,0 +1,91 @@
+use super::format_context::FormatContext;
+use super::format_string::{FormatFields, FormatString};
+use serde::{Deserialize, Serialize};
+
+/// Structure-aware edits of a LyX document.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LyxEditSettings {
+    title_format: Option<FormatString>,
+    author_format: Option<FormatString>,
+    date_format: Option<FormatString>,
+    sections: Option<SectionsSettings>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SectionsSettings {
+    count: usize,
+    title_format: FormatString,
+    #[serde(default = "default_section_layout")]
+    layout: Box<str>,
+    marker: Option<Box<str>>,
+}
+
+fn default_section_layout() -> Box<str> {
+    "Section".into()
+}
+
+impl LyxEditSettings {
+    /// The text of the `Title`, `Author` and `Date` paragraphs to set, by layout.
+    pub fn header_fields(
+        &self,
+        num: usize,
+        context: &FormatContext,
+    ) -> anyhow::Result<Vec<(&'static str, String)>> {
+        [
+            ("Title", &self.title_format),
+            ("Author", &self.author_format),
+            ("Date", &self.date_format),
+        ]
+        .into_iter()
+        .filter_map(|(layout, format)| Some((layout, format.as_ref()?)))
+        .map(|(layout, format)| {
+            let text = context.format(format, &[("num", &num)])?;
+            Ok((layout, text))
+        })
+        .collect()
+    }
+
+    pub(super) fn format_fields<'a>(&'a self, key: &str, fields: &mut FormatFields<'a>) {
+        for (name, format) in [
+            ("title_format", &self.title_format),
+            ("author_format", &self.author_format),
+            ("date_format", &self.date_format),
+        ] {
+            if let Some(format) = format {
+                fields.add(format_args!("{key}.{name}"), format, &["num"]);
+            }
+        }
+        if let Some(sections) = &self.sections {
+            fields.add(
+                format_args!("{key}.sections.title_format"),
+                &sections.title_format,
+                &["num", "index"],
+            );
+        }
+    }
+
+    pub fn sections(&self) -> Option<&SectionsSettings> {
+        self.sections.as_ref()
+    }
+}
+
+impl SectionsSettings {
+    /// The titles of the sections to insert.
+    pub fn titles(&self, num: usize, context: &FormatContext) -> anyhow::Result<Box<[String]>> {
+        (1..=self.count)
+            .map(|index| context.format(&self.title_format, &[("num", &num), ("index", &index)]))
+            .collect()
+    }
+
+    pub fn layout(&self) -> &str {
+        &self.layout
+    }
+
+    /// Text in the template that marks where to insert the sections.
+    /// The paragraph containing it is replaced by the sections.
+    pub fn marker(&self) -> Option<&str> {
+        self.marker.as_deref()
+    }
+}
@@ -1,17 +1,21 @@
+use super::format_context::FormatContext;
+use super::format_string::{FormatFields, FormatString};
 use serde::{Deserialize, Serialize};
 use std::marker::PhantomData;
 
 pub trait Formattable {
     type Params: ?Sized;
+    /// The names of the arguments of `args_format`.
+    const ARGS: &'static [&'static str];
 
-    fn format(s: String, params: &Self::Params) -> anyhow::Result<String>;
+    fn format(s: &str, params: &Self::Params, context: &FormatContext) -> anyhow::Result<String>;
 }
 
 #[derive(Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct OpenSettings<T: Formattable> {
     binary: Box<str>,
-    args_format: Box<[Box<str>]>,
+    args_format: Box<[FormatString]>,
 
     #[serde(skip)]
     _phantom: PhantomData<T>,
@@ -32,12 +36,23 @@ impl<T: Formattable> OpenSettings<T> {
         &self.binary
     }
 
-    pub fn args(&self, params: &T::Params) -> anyhow::Result<impl Iterator<Item = String>> {
+    pub fn args(
+        &self,
+        params: &T::Params,
+        context: &FormatContext,
+    ) -> anyhow::Result<impl Iterator<Item = String>> {
         self.args_format
             .iter()
-            .map(|arg_format| T::format(arg_format.to_string(), params))
+            .map(|arg_format| T::format(arg_format, params, context))
             .collect::<Result<Vec<_>, _>>()
-            .map_err(Into::into)
             .map(IntoIterator::into_iter)
     }
+
+    pub(super) fn format_fields<'a>(&'a self, key: &str, fields: &mut FormatFields<'a>) {
+        fields.add_all(
+            format_args!("{key}.args_format"),
+            &self.args_format,
+            T::ARGS,
+        );
+    }
 }
@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Personal details of the student (and their partners), for headers and submission filenames.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileSettings {
+    name: Option<Box<str>>,
+    id: Option<Box<str>>,
+    email: Option<Box<str>>,
+    /// The name in other languages, by language code (e.g. `he`).
+    #[serde(default)]
+    localized_names: BTreeMap<Box<str>, Box<str>>,
+    #[serde(default)]
+    partners: Box<[PartnerSettings]>,
+}
+
+/// A student who submits the HWs together with the user.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PartnerSettings {
+    name: Box<str>,
+    id: Box<str>,
+    #[serde(default)]
+    localized_names: BTreeMap<Box<str>, Box<str>>,
+}
+
+impl ProfileSettings {
+    /// The named arguments that the profile provides to format specifiers.
+    /// Fields that are not set do not provide arguments.
+    pub fn args(&self) -> Vec<(String, String)> {
+        let mut args = Vec::new();
+        if let Some(name) = &self.name {
+            args.push(("student_name".to_owned(), name.to_string()));
+            args.push((
+                "student_names".to_owned(),
+                self.join(name, |partner| &partner.name, ", "),
+            ));
+        }
+        if let Some(id) = &self.id {
+            args.push(("student_id".to_owned(), id.to_string()));
+            args.push((
+                "student_ids".to_owned(),
+                self.join(id, |partner| &partner.id, "_"),
+            ));
+        }
+        if let Some(email) = &self.email {
+            args.push(("student_email".to_owned(), email.to_string()));
+        }
+        for (language, name) in &self.localized_names {
+            args.push((format!("student_name_{language}"), name.to_string()));
+            args.push((
+                format!("student_names_{language}"),
+                self.join(
+                    name,
+                    |partner| {
+                        partner
+                            .localized_names
+                            .get(language)
+                            .unwrap_or(&partner.name)
+                    },
+                    ", ",
+                ),
+            ));
+        }
+        args
+    }
+
+    /// Join a field of the user with the same field of each partner.
+    fn join(&self, own: &str, field: impl Fn(&PartnerSettings) -> &str, separator: &str) -> String {
+        std::iter::once(own)
+            .chain(self.partners.iter().map(field))
+            .collect::<Vec<_>>()
+            .join(separator)
+    }
+}
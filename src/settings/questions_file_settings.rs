@@ -1,15 +1,30 @@
+use super::archive_settings::ArchiveSettings;
+use super::attachments_settings::AttachmentsSettings;
+use super::download_filter::DownloadFilter;
+use super::format_context::FormatContext;
+use super::format_matcher::FormatMatcher;
+use super::format_string::{FormatFields, FormatString};
 use super::open_settings::{Formattable, OpenSettings};
-use formatx::formatx;
 use serde::{Deserialize, Serialize};
-use std::path::{self, Path};
+use std::{
+    ffi::OsStr,
+    path::{self, Path},
+};
 
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct QuestionsFileSettings {
     downloads_dir: Box<Path>,
-    questions_filename_format: Box<str>,
+    questions_filename_format: FormatString,
+    choose_from_recent: Option<usize>,
+    #[serde(default)]
+    filter: DownloadFilter,
 
     open: Option<OpenSettings<QuestionsFile>>,
+
+    extract: Option<ArchiveSettings>,
+
+    attachments: Option<AttachmentsSettings>,
 }
 
 impl QuestionsFileSettings {
@@ -17,25 +32,88 @@ impl QuestionsFileSettings {
         &self.downloads_dir
     }
 
-    pub fn questions_filename(&self, num: usize) -> Result<String, formatx::Error> {
-        formatx!(self.questions_filename_format.to_owned(), num = num)
+    pub(super) fn format_fields<'a>(&'a self, key: &str, fields: &mut FormatFields<'a>) {
+        fields.add(
+            format_args!("{key}.questions_filename_format"),
+            &self.questions_filename_format,
+            &["num", "ext", "stem", "original_name"],
+        );
+        if let Some(open) = &self.open {
+            open.format_fields(&format!("{key}.open"), fields);
+        }
+        if let Some(extract) = &self.extract {
+            extract.format_fields(&format!("{key}.extract"), fields);
+        }
+        if let Some(attachments) = &self.attachments {
+            attachments.format_fields(&format!("{key}.attachments"), fields);
+        }
+    }
+
+    /// The questions filename, for a questions file moved from `download`.
+    pub fn questions_filename(
+        &self,
+        num: usize,
+        download: &Path,
+        context: &FormatContext,
+    ) -> anyhow::Result<String> {
+        context.format(
+            &self.questions_filename_format,
+            &[
+                ("num", &num),
+                ("ext", &name_part(download.extension())),
+                ("stem", &name_part(download.file_stem())),
+                ("original_name", &name_part(download.file_name())),
+            ],
+        )
+    }
+
+    /// Compile `questions_filename_format` into a matcher that recognizes questions filenames,
+    /// whatever the name of the original download was.
+    pub fn questions_filename_matcher(&self) -> anyhow::Result<FormatMatcher> {
+        FormatMatcher::new(&self.questions_filename_format, "num")
+    }
+
+    /// Number of recent downloads to let the user choose the questions file from.
+    /// If `None`, the most recent download is used.
+    pub fn choose_from_recent(&self) -> Option<usize> {
+        self.choose_from_recent
+    }
+
+    pub fn filter(&self) -> &DownloadFilter {
+        &self.filter
     }
 
     pub fn open_settings(&self) -> Option<&OpenSettings<QuestionsFile>> {
         self.open.as_ref()
     }
+
+    /// Settings for extracting questions files that are archives.
+    /// If `None`, archives are moved as-is.
+    pub fn archive_settings(&self) -> Option<&ArchiveSettings> {
+        self.extract.as_ref()
+    }
+
+    pub fn attachments_settings(&self) -> Option<&AttachmentsSettings> {
+        self.attachments.as_ref()
+    }
+}
+
+/// A part of the name of a downloaded file, as a format argument.
+pub(super) fn name_part(s: Option<&OsStr>) -> String {
+    s.map_or_else(String::new, |s| s.to_string_lossy().into_owned())
 }
 
 pub struct QuestionsFile;
 impl Formattable for QuestionsFile {
     type Params = Path;
+    const ARGS: &'static [&'static str] = &["questions_file"];
 
-    fn format(s: String, questions_file: &Path) -> anyhow::Result<String> {
+    fn format(s: &str, questions_file: &Path, context: &FormatContext) -> anyhow::Result<String> {
         let absolute_questions_file = path::absolute(questions_file)?;
         let questions_file = absolute_questions_file
             .to_str()
             .ok_or(anyhow::anyhow!("cannot convert questions file to string"))?;
 
-        formatx!(s, questions_file = questions_file).map_err(Into::into)
+        context.format(s, &[("questions_file", &questions_file)])
     }
 }
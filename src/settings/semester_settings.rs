@@ -0,0 +1,110 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+/// The semester's calendar, for the teaching-week number of a date.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SemesterSettings {
+    start: NaiveDate,
+    end: NaiveDate,
+    #[serde(default)]
+    breaks: Box<[BreakSettings]>,
+}
+
+/// A break (or a holiday) in the semester. Without an `end`, it is a single day.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BreakSettings {
+    start: NaiveDate,
+    end: Option<NaiveDate>,
+}
+
+impl SemesterSettings {
+    /// The teaching week of `date`: 1 in the week the semester starts, 0 before it.
+    ///
+    /// Weeks are counted in 7-day blocks from `start`. A block that falls entirely within breaks is not counted,
+    /// so during such a block (and after `end`), the week is the last teaching week before it.
+    pub fn week(&self, date: NaiveDate) -> usize {
+        let date = date.min(self.end);
+
+        let mut week = 0;
+        let mut block_start = self.start;
+        while block_start <= date {
+            let block_end = block_start + chrono::Days::new(6);
+            if !self.is_break(block_start, block_end) {
+                week += 1;
+            }
+            block_start = block_end + chrono::Days::new(1);
+        }
+        week
+    }
+
+    /// Whether every day from `first` to `last` (inclusive) is in a break.
+    fn is_break(&self, first: NaiveDate, last: NaiveDate) -> bool {
+        first.iter_days().take_while(|day| *day <= last).all(|day| {
+            self.breaks
+                .iter()
+                .any(|break_| break_.start <= day && day <= break_.end.unwrap_or(break_.start))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, month, day).unwrap()
+    }
+
+    /// A semester that starts on Sunday 2026-10-18, with a two-week break and a single-day holiday.
+    fn semester() -> SemesterSettings {
+        SemesterSettings {
+            start: date(10, 18),
+            end: date(12, 31),
+            breaks: Box::new([
+                BreakSettings {
+                    start: date(10, 25),
+                    end: Some(date(11, 7)),
+                },
+                BreakSettings {
+                    start: date(11, 10),
+                    end: None,
+                },
+            ]),
+        }
+    }
+
+    #[test]
+    fn before_start() {
+        assert_eq!(semester().week(date(10, 17)), 0);
+    }
+
+    #[test]
+    fn first_week() {
+        assert_eq!(semester().week(date(10, 18)), 1);
+        assert_eq!(semester().week(date(10, 24)), 1);
+    }
+
+    #[test]
+    fn during_break() {
+        // the weeks within the break are not counted
+        assert_eq!(semester().week(date(10, 25)), 1);
+        assert_eq!(semester().week(date(11, 7)), 1);
+        assert_eq!(semester().week(date(11, 8)), 2);
+    }
+
+    #[test]
+    fn partial_break() {
+        // a single holiday does not skip its week
+        assert_eq!(semester().week(date(11, 10)), 2);
+        assert_eq!(semester().week(date(11, 15)), 3);
+    }
+
+    #[test]
+    fn after_end() {
+        let last = semester().week(date(12, 31));
+        assert_eq!(semester().week(date(12, 31).succ_opt().unwrap()), last);
+        assert_eq!(last, 9);
+    }
+}
@@ -0,0 +1,51 @@
+use super::format_context::FormatContext;
+use super::format_string::{FormatFields, FormatString};
+use glob::Pattern;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Settings for packaging a HW for submission, according to the course's naming rules.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SubmissionSettings {
+    filename_format: FormatString,
+    include: Box<[Box<str>]>,
+    #[serde(default)]
+    zip: bool,
+    outbox_dir: Option<Box<Path>>,
+}
+
+impl SubmissionSettings {
+    pub(super) fn format_fields<'a>(&'a self, key: &str, fields: &mut FormatFields<'a>) {
+        fields.add(
+            format_args!("{key}.filename_format"),
+            &self.filename_format,
+            &["num"],
+        );
+    }
+
+    pub fn filename(&self, num: usize, context: &FormatContext) -> anyhow::Result<String> {
+        context.format(&self.filename_format, &[("num", &num)])
+    }
+
+    /// Whether the file at `relative_path` (relative to the HW directory) is a part of the submission.
+    pub fn includes(&self, relative_path: &Path) -> anyhow::Result<bool> {
+        for include in self.include.iter() {
+            if Pattern::new(include)?.matches_path(relative_path) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Whether the included files are packaged in a zip file.
+    /// Otherwise, exactly one file has to be included, and it is copied as-is.
+    pub fn zip(&self) -> bool {
+        self.zip
+    }
+
+    /// The directory to put the submission in. If `None`, it is put in the HW directory.
+    pub fn outbox_dir(&self) -> Option<&Path> {
+        self.outbox_dir.as_deref()
+    }
+}
@@ -0,0 +1,52 @@
+use super::document_settings::ReplacementConfig;
+use super::format_context::FormatContext;
+use super::format_string::FormatFields;
+use glob::Pattern;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Settings for a directory tree that is copied into every new HW directory.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TemplateDirSettings {
+    path: Box<Path>,
+    #[serde(default)]
+    format_globs: Box<[Box<str>]>,
+    #[serde(default)]
+    replacements: Box<[ReplacementConfig]>,
+}
+
+impl TemplateDirSettings {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub(super) fn format_fields<'a>(&'a self, key: &str, fields: &mut FormatFields<'a>) {
+        ReplacementConfig::format_fields(&self.replacements, key, fields);
+    }
+
+    /// Whether the file at `relative_path` (relative to the template directory) matches one of `format_globs`,
+    /// i.e. whether its filename is formatted and its contents are replaced.
+    pub fn is_formatted(&self, relative_path: &Path) -> anyhow::Result<bool> {
+        for format_glob in self.format_globs.iter() {
+            if Pattern::new(format_glob)?.matches_path(relative_path) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Format the filename of a file that matches one of `format_globs`.
+    pub fn filename(
+        &self,
+        filename: &str,
+        num: usize,
+        context: &FormatContext,
+    ) -> anyhow::Result<String> {
+        context.format(filename, &[("num", &num)])
+    }
+
+    pub fn replacements(&self) -> &[ReplacementConfig] {
+        &self.replacements
+    }
+}
@@ -2,7 +2,22 @@ use crate::settings::Settings;
 use std::ffi::OsStr;
 use std::path::{Path, PathBuf};
 
+mod archive;
+mod checks;
+mod doctor;
+mod downloads;
 mod files;
+mod journal;
+mod lyx;
+mod plan;
+mod submission;
+mod view;
+
+pub use checks::CheckReport;
+pub use doctor::DoctorReport;
+pub use downloads::Download;
+pub use plan::{ExportPlan, NewHwOptions, NewHwPlan, OpenPlan, PackagePlan};
+pub use view::{HwView, SubjectView};
 
 #[derive(Clone)]
 pub struct Subject {
@@ -24,11 +39,21 @@ impl Subject {
     pub fn current_hw_num(&self) -> usize {
         self.current_hw_num
     }
+
+    /// The effective settings of this subject (including its per-subject settings file).
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
 }
 
 impl Subject {
     pub fn from_directory(settings: &Settings, dir: PathBuf) -> anyhow::Result<Self> {
         let settings = files::update_subject_settings(settings, &dir)?;
+        Self::with_settings(settings, dir)
+    }
+
+    /// Load a subject whose effective settings (see [`Self::settings`]) were already computed.
+    fn with_settings(settings: Settings, dir: PathBuf) -> anyhow::Result<Self> {
         let name = dir
             .file_name()
             .and_then(OsStr::to_str)
@@ -43,6 +68,7 @@ impl Subject {
         })
     }
 
+    /// The subjects that can be loaded; `doctor` reports the ones that cannot.
     pub fn get_all_subjects(settings: &Settings) -> anyhow::Result<Box<[Self]>> {
         Ok(settings.subject_ordering().sort_subjects(
             settings
@@ -53,13 +79,158 @@ impl Subject {
         ))
     }
 
+    /// Look for problems in the settings (including the per-subject settings files) and in the subjects,
+    /// e.g. missing directories, openers that are not installed, and subject directories that cannot be loaded.
+    pub fn doctor(settings: &Settings) -> anyhow::Result<DoctorReport> {
+        doctor::diagnose(settings)
+    }
+
+    /// Find a subject by its name or by its index in `subjects`.
+    pub fn find<'a>(subjects: &'a mut [Self], name_or_index: &str) -> Option<&'a mut Self> {
+        if let Ok(i) = name_or_index.parse::<usize>() {
+            if i < subjects.len() {
+                return Some(&mut subjects[i]);
+            }
+        }
+
+        subjects
+            .iter_mut()
+            .find(|subject| subject.name() == name_or_index)
+    }
+
     pub fn open_last_hw(&self) -> anyhow::Result<()> {
         files::open_last_hw_dir(&self.settings, &self.dir)
     }
 
-    pub fn create_new_hw_dir(&mut self) -> anyhow::Result<()> {
-        files::create_new_hw_dir(&self.settings, &self.dir)?;
-        self.current_hw_num += 1;
+    /// List the existing HW directories in this subject, sorted by HW number.
+    pub fn list_hws(&self) -> anyhow::Result<Box<[(usize, PathBuf)]>> {
+        files::list_hw_dirs(&self.settings, &self.dir)
+    }
+
+    pub fn open_hw(&self, num: usize) -> anyhow::Result<()> {
+        files::open_existing_hw_dir(&self.settings, &self.dir, num)
+    }
+
+    /// Compute what opening a HW would do.
+    pub fn plan_open(&self, num: usize) -> anyhow::Result<OpenPlan> {
+        files::plan_open(&self.settings, &self.dir, num)
+    }
+
+    /// Open a HW, according to a plan computed by [`Self::plan_open`].
+    pub fn open(&self, plan: &OpenPlan) -> anyhow::Result<()> {
+        files::open_hw(plan)
+    }
+
+    /// The path of an existing HW directory in this subject.
+    pub fn hw_dir(&self, num: usize) -> anyhow::Result<PathBuf> {
+        files::existing_hw_dir(&self.settings, &self.dir, num)
+    }
+
+    /// Compute what exporting the LyX file of a HW would do.
+    pub fn plan_export(&self, num: usize) -> anyhow::Result<ExportPlan> {
+        files::plan_export(&self.settings, &self.dir, num)
+    }
+
+    /// Export the LyX file of a HW (e.g. to PDF), according to a plan computed by [`Self::plan_export`].
+    pub fn export(&self, plan: &ExportPlan) -> anyhow::Result<()> {
+        files::export_hw(plan)
+    }
+
+    /// Compute what packaging a HW for submission would do.
+    pub fn plan_package(&self, num: usize) -> anyhow::Result<PackagePlan> {
+        submission::plan_package(&self.settings, &self.dir, num)
+    }
+
+    /// Package a HW for submission, according to a plan computed by [`Self::plan_package`].
+    pub fn package(&self, plan: &PackagePlan) -> anyhow::Result<()> {
+        submission::package(plan)
+    }
+
+    /// Run the checks configured in the settings on a HW, before submitting it.
+    pub fn check(&self, num: usize) -> anyhow::Result<CheckReport> {
+        checks::run_checks(&self.settings, &self.dir, num)
+    }
+
+    /// A machine-readable description of this subject and its HW directories.
+    pub fn view(&self) -> anyhow::Result<SubjectView> {
+        SubjectView::new(self)
+    }
+
+    /// A machine-readable description of an existing HW directory in this subject.
+    pub fn hw_view(&self, num: usize) -> anyhow::Result<HwView> {
+        HwView::new(num, self.hw_dir(num)?)
+    }
+
+    pub fn create_new_hw_dir(&mut self, options: &NewHwOptions) -> anyhow::Result<()> {
+        self.current_hw_num = files::create_new_hw_dir(&self.settings, &self.dir, options)?;
+        Ok(())
+    }
+
+    /// Compute what creating a new HW directory with the given options would do,
+    /// without touching the filesystem.
+    pub fn plan_new_hw_dir(&self, options: &NewHwOptions) -> anyhow::Result<NewHwPlan> {
+        files::plan_new_hw_dir(&self.settings, &self.dir, options)
+    }
+
+    /// The most recent downloads to choose the questions file from,
+    /// if the settings ask for an interactive choice.
+    pub fn questions_file_choices(&self) -> anyhow::Result<Option<Box<[Download]>>> {
+        let Some(questions_file_settings) = self.settings.questions_file_settings() else {
+            return Ok(None);
+        };
+        let Some(count) = questions_file_settings.choose_from_recent() else {
+            return Ok(None);
+        };
+        downloads::recent_downloads(
+            questions_file_settings.downloads_dir(),
+            questions_file_settings.filter(),
+            count,
+        )
+        .map(Some)
+    }
+
+    /// The downloads to choose the attachments from, if the settings ask for an interactive choice.
+    /// `questions_file` is the chosen questions file, if it was chosen interactively.
+    pub fn attachment_choices(
+        &self,
+        questions_file: Option<&Path>,
+    ) -> anyhow::Result<Option<Box<[Download]>>> {
+        let Some(questions_file_settings) = self.settings.questions_file_settings() else {
+            return Ok(None);
+        };
+        let Some(attachments_settings) = questions_file_settings
+            .attachments_settings()
+            .filter(|attachments_settings| attachments_settings.choose_interactively())
+        else {
+            return Ok(None);
+        };
+
+        let most_recent_download;
+        let questions_file = match questions_file {
+            Some(questions_file) => Some(questions_file),
+            None => {
+                most_recent_download = downloads::get_most_recent_download(
+                    questions_file_settings.downloads_dir(),
+                    questions_file_settings.filter(),
+                )
+                .ok();
+                most_recent_download.as_deref()
+            }
+        };
+
+        downloads::select_attachments(
+            questions_file_settings.downloads_dir(),
+            attachments_settings,
+            questions_file,
+            true,
+        )
+        .map(Some)
+    }
+
+    /// Create a new HW directory exactly as described by a plan from [`Subject::plan_new_hw_dir`].
+    pub fn create_new_hw_dir_from_plan(&mut self, plan: &NewHwPlan) -> anyhow::Result<()> {
+        files::execute_plan(plan)?;
+        self.current_hw_num = plan.num();
         Ok(())
     }
 }
@@ -0,0 +1,202 @@
+use super::journal::Journal;
+use std::{
+    fs,
+    io::Read,
+    path::{Component, Path, PathBuf},
+};
+
+/// A supported archive format.
+#[derive(Clone, Copy)]
+pub enum ArchiveKind {
+    Zip,
+    TarGz,
+}
+
+impl ArchiveKind {
+    /// Detect the archive format from the filename of `path`.
+    /// Also returns the filename without the archive extension (e.g. `hw1` for `hw1.tar.gz`).
+    pub fn detect(path: &Path) -> Option<(Self, String)> {
+        let name = path.file_name()?.to_str()?;
+        let lowercase_name = name.to_ascii_lowercase();
+        [
+            (".zip", ArchiveKind::Zip),
+            (".tar.gz", ArchiveKind::TarGz),
+            (".tgz", ArchiveKind::TarGz),
+        ]
+        .into_iter()
+        .find(|(ext, _kind)| lowercase_name.ends_with(ext))
+        .map(|(ext, kind)| (kind, name[..name.len() - ext.len()].to_owned()))
+    }
+}
+
+/// List the files in an archive, as paths relative to the extraction directory.
+pub fn list_files(archive: &Path, kind: ArchiveKind) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for_each_file(archive, kind, |path, _contents| {
+        files.push(path);
+        Ok(())
+    })?;
+    Ok(files)
+}
+
+/// Extract an archive into `dir` (which is created if needed).
+pub fn extract(
+    archive: &Path,
+    kind: ArchiveKind,
+    dir: &Path,
+    journal: &mut Journal,
+) -> anyhow::Result<()> {
+    journal.create_dir_all(dir)?;
+    for_each_file(archive, kind, |path, contents| {
+        let dest = dir.join(path);
+        if let Some(parent) = dest.parent() {
+            journal.create_dir_all(parent)?;
+        }
+
+        let mut data = Vec::new();
+        contents.read_to_end(&mut data)?;
+        journal.write_file(&dest, data)
+    })
+}
+
+/// Call `f` with the path and the contents of every regular file in the archive.
+/// Fails on paths that would escape the extraction directory.
+fn for_each_file(
+    archive: &Path,
+    kind: ArchiveKind,
+    mut f: impl FnMut(PathBuf, &mut dyn Read) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let file = fs::File::open(archive)?;
+    match kind {
+        ArchiveKind::Zip => {
+            let mut zip = zip::ZipArchive::new(file)?;
+            for i in 0..zip.len() {
+                let mut entry = zip.by_index(i)?;
+                if entry.is_dir() {
+                    continue;
+                }
+                let path = entry
+                    .enclosed_name()
+                    .ok_or_else(|| anyhow::anyhow!("unsafe path in archive: {}", entry.name()))?;
+                f(path, &mut entry)?;
+            }
+        }
+        ArchiveKind::TarGz => {
+            let mut tar = tar::Archive::new(flate2::read::GzDecoder::new(file));
+            for entry in tar.entries()? {
+                let mut entry = entry?;
+                if !entry.header().entry_type().is_file() {
+                    continue;
+                }
+                let path = entry.path()?.into_owned();
+                if !path
+                    .components()
+                    .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+                {
+                    anyhow::bail!("unsafe path in archive: {}", path.display());
+                }
+                f(path, &mut entry)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
+    use std::io::Write;
+
+    fn zip_archive(dir: &TempDir, names: &[&str]) -> PathBuf {
+        let path = dir.path().join("archive.zip");
+        let mut zip = zip::ZipWriter::new(fs::File::create(&path).unwrap());
+        for name in names {
+            zip.start_file(*name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(name.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+        path
+    }
+
+    fn tar_gz_archive(dir: &TempDir, names: &[&str]) -> PathBuf {
+        let path = dir.path().join("archive.tar.gz");
+        let gz = flate2::write::GzEncoder::new(
+            fs::File::create(&path).unwrap(),
+            flate2::Compression::default(),
+        );
+        let mut tar = tar::Builder::new(gz);
+        for name in names {
+            // set the name directly, since `Header::set_path` rejects unsafe paths
+            let mut header = tar::Header::new_old();
+            header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.set_size(name.len() as u64);
+            header.set_entry_type(tar::EntryType::Regular);
+            header.set_cksum();
+            tar.append(&header, name.as_bytes()).unwrap();
+        }
+        tar.into_inner().unwrap().finish().unwrap();
+        path
+    }
+
+    #[test]
+    fn safe_paths() {
+        let dir = TempDir::new("archive_safe_paths");
+        let names = ["hw1.pdf", "skel/main.py"];
+        for (archive, kind) in [
+            (zip_archive(&dir, &names), ArchiveKind::Zip),
+            (tar_gz_archive(&dir, &names), ArchiveKind::TarGz),
+        ] {
+            assert_eq!(
+                list_files(&archive, kind).unwrap(),
+                names.map(PathBuf::from)
+            );
+
+            let dest = dir.path().join("extracted");
+            let mut journal = Journal::new();
+            extract(&archive, kind, &dest, &mut journal).unwrap();
+            assert_eq!(
+                fs::read_to_string(dest.join("skel/main.py")).unwrap(),
+                "skel/main.py"
+            );
+            journal.rollback().unwrap();
+        }
+    }
+
+    #[test]
+    fn unsafe_paths() {
+        let dir = TempDir::new("archive_unsafe_paths");
+        for name in ["../escape.txt", "skel/../../escape.txt", "/tmp/escape.txt"] {
+            for (archive, kind) in [
+                (zip_archive(&dir, &["hw1.pdf", name]), ArchiveKind::Zip),
+                (tar_gz_archive(&dir, &["hw1.pdf", name]), ArchiveKind::TarGz),
+            ] {
+                let err = list_files(&archive, kind).unwrap_err();
+                assert!(
+                    err.to_string().starts_with("unsafe path in archive"),
+                    "{err}"
+                );
+
+                let dest = dir.path().join("extracted");
+                let mut journal = Journal::new();
+                assert!(extract(&archive, kind, &dest, &mut journal).is_err());
+                journal.rollback().unwrap();
+                assert!(!dest.exists());
+                assert!(!dir.path().join("escape.txt").exists());
+            }
+        }
+    }
+
+    #[test]
+    fn detect() {
+        let detect = |name: &str| {
+            ArchiveKind::detect(Path::new(name))
+                .map(|(kind, stem)| (matches!(kind, ArchiveKind::Zip), stem))
+        };
+        assert_eq!(detect("hw1.ZIP"), Some((true, "hw1".to_owned())));
+        assert_eq!(detect("hw1.tar.gz"), Some((false, "hw1".to_owned())));
+        assert_eq!(detect("hw1.tgz"), Some((false, "hw1".to_owned())));
+        assert_eq!(detect("hw1.pdf"), None);
+    }
+}
@@ -0,0 +1,270 @@
+use super::files::{self, update_subject_settings};
+use crate::settings::Settings;
+use regex::Regex;
+use std::{
+    fmt::{self, Display},
+    fs,
+    ops::Range,
+    path::Path,
+};
+
+/// The results of the checks that are run on a HW before submitting it.
+pub struct CheckReport {
+    results: Box<[CheckResult]>,
+}
+
+struct CheckResult {
+    name: String,
+    /// `None` if the check passed, otherwise why it failed.
+    failure: Option<String>,
+}
+
+impl CheckReport {
+    pub fn passed(&self) -> bool {
+        self.results.iter().all(|result| result.failure.is_none())
+    }
+}
+
+/// Run the checks configured in the settings on the HW with the given number.
+pub fn run_checks(
+    settings: &Settings,
+    subject_dir: &Path,
+    num: usize,
+) -> anyhow::Result<CheckReport> {
+    let subject_settings = update_subject_settings(settings, subject_dir)?;
+    let Some(checks_settings) = subject_settings.checks_settings() else {
+        anyhow::bail!("no checks are configured (see `checks` in the settings)");
+    };
+    let hw_dir = files::existing_hw_dir(&subject_settings, subject_dir, num)?;
+
+    let mut results = Vec::new();
+    if checks_settings.export_up_to_date() {
+        results.push(CheckResult {
+            name: "exported file is up to date".to_owned(),
+            failure: check_export(settings, subject_dir, num)?,
+        });
+    }
+    if checks_settings.no_placeholders() {
+        results.push(CheckResult {
+            name: "no placeholders remain".to_owned(),
+            failure: check_placeholders(&subject_settings, &hw_dir, num)?,
+        });
+    }
+    let required_files = checks_settings.required_files()?;
+    if !required_files.is_empty() {
+        let hw_files = hw_files(&hw_dir)?;
+        for pattern in required_files {
+            let found = hw_files.iter().any(|file| pattern.matches_path(file));
+            results.push(CheckResult {
+                name: format!("{:?} exists", pattern.as_str()),
+                failure: (!found).then(|| "no such file".to_owned()),
+            });
+        }
+    }
+    if let Some(max_size_kb) = checks_settings.max_size_kb() {
+        results.push(CheckResult {
+            name: format!("submitted files are at most {max_size_kb} KB"),
+            failure: check_sizes(&subject_settings, &hw_dir, max_size_kb)?,
+        });
+    }
+
+    Ok(CheckReport {
+        results: results.into(),
+    })
+}
+
+fn check_export(
+    settings: &Settings,
+    subject_dir: &Path,
+    num: usize,
+) -> anyhow::Result<Option<String>> {
+    let plan = match files::plan_export(settings, subject_dir, num) {
+        Ok(plan) => plan,
+        Err(err) => return Ok(Some(format!("{err:#}"))),
+    };
+    let Ok(output_meta) = fs::metadata(&plan.output) else {
+        return Ok(Some(format!("{} does not exist", plan.output.display())));
+    };
+    if output_meta.modified()? < fs::metadata(&plan.lyx_file)?.modified()? {
+        return Ok(Some(format!(
+            "{} is older than {}",
+            plan.output.display(),
+            plan.lyx_file.display()
+        )));
+    }
+    Ok(None)
+}
+
+fn check_placeholders(
+    settings: &Settings,
+    hw_dir: &Path,
+    num: usize,
+) -> anyhow::Result<Option<String>> {
+    let mut failures = Vec::new();
+    let context = settings.format_context();
+    for document_settings in settings.documents().iter() {
+        if document_settings.replacements().is_empty() {
+            continue;
+        }
+        let document = files::document_path(settings, document_settings, hw_dir, num)?;
+        let data = match fs::read_to_string(&document) {
+            Ok(data) => data,
+            Err(err) => {
+                failures.push(format!("{} cannot be read ({err})", document.display()));
+                continue;
+            }
+        };
+        for replace in document_settings.replacements() {
+            // with a `count`, the later appearances are left in place on purpose
+            if replace.count().is_some() {
+                continue;
+            }
+            // the replacement text may contain `from` itself (e.g. a name replaced with a longer name),
+            // so only the appearances outside of it are placeholders
+            let to = replace.to(num, &context)?;
+            let replaced: Vec<_> = if replace.expands_captures() || to.is_empty() {
+                Vec::new()
+            } else {
+                data.match_indices(&to)
+                    .map(|(start, to)| start..start + to.len())
+                    .collect()
+            };
+            if remains(&replace.regex()?, &data, &replaced) {
+                failures.push(format!("{:?} in {}", replace.from(), document.display()));
+            }
+        }
+    }
+
+    Ok((!failures.is_empty()).then(|| failures.join(", ")))
+}
+
+/// Whether `regex` matches `data` anywhere outside of the `replaced` ranges.
+fn remains(regex: &Regex, data: &str, replaced: &[Range<usize>]) -> bool {
+    let mut start = 0;
+    while let Some(found) = regex.find_at(data, start) {
+        let inside = replaced
+            .iter()
+            .any(|range| range.start <= found.start() && found.end() <= range.end);
+        if !inside {
+            return true;
+        }
+        // the matches of `regex` may overlap, so look for the next one right after the start of this one
+        start = found.start()
+            + data[found.start()..]
+                .chars()
+                .next()
+                .map_or(1, char::len_utf8);
+        if start > data.len() {
+            break;
+        }
+    }
+    false
+}
+
+/// Check the sizes of the files that would be submitted
+/// (or of all the files in the HW directory, if submitting is not configured).
+fn check_sizes(
+    settings: &Settings,
+    hw_dir: &Path,
+    max_size_kb: u64,
+) -> anyhow::Result<Option<String>> {
+    let mut too_large = Vec::new();
+    for file in hw_files(hw_dir)? {
+        if let Some(submission_settings) = settings.submission_settings() {
+            if !submission_settings.includes(&file)? {
+                continue;
+            }
+        }
+        let size_kb = fs::metadata(hw_dir.join(&file))?.len().div_ceil(1024);
+        if size_kb > max_size_kb {
+            too_large.push(format!("{} ({size_kb} KB)", file.display()));
+        }
+    }
+
+    Ok((!too_large.is_empty()).then(|| too_large.join(", ")))
+}
+
+/// The files in a HW directory (recursively), relative to it.
+fn hw_files(hw_dir: &Path) -> anyhow::Result<Vec<std::path::PathBuf>> {
+    Ok(files::walk_dir(hw_dir)?
+        .into_iter()
+        .filter(|file| hw_dir.join(file).is_file())
+        .collect())
+}
+
+impl Display for CheckReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for result in self.results.iter() {
+            match &result.failure {
+                None => writeln!(f, "[PASS] {}", result.name)?,
+                Some(failure) => writeln!(f, "[FAIL] {}: {failure}", result.name)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    const SETTINGS: &str = r#",
+        "documents": [ { "filename_format": "sol{num}.tex", "replacements": [ { "from": "NAME", "to_format": "NAME{num}" } ] } ],
+        "checks": { "no_placeholders": true, "required_files": ["*.pdf"], "max_size_kb": 1 }
+    "#;
+
+    fn failures(report: &CheckReport) -> Vec<(&str, &str)> {
+        report
+            .results
+            .iter()
+            .filter_map(|result| Some((result.name.as_str(), result.failure.as_deref()?)))
+            .collect()
+    }
+
+    #[test]
+    fn passing_checks() {
+        let dir = TempDir::new("passing_checks");
+        let settings = dir.settings(SETTINGS, 2);
+        // `NAME` appears only as part of the replacement text
+        dir.write("subjects/A/HW3/sol3.tex", "NAME3 and NAME3");
+        dir.write("subjects/A/HW3/hw3.pdf", "");
+
+        let report = run_checks(&settings, &dir.path().join("subjects/A"), 3).unwrap();
+        assert!(report.passed(), "{report}");
+    }
+
+    #[test]
+    fn failing_checks() {
+        let dir = TempDir::new("failing_checks");
+        let settings = dir.settings(SETTINGS, 2);
+        let document = dir.write("subjects/A/HW3/sol3.tex", &"NAME3 NAME ".repeat(100));
+
+        let report = run_checks(&settings, &dir.path().join("subjects/A"), 3).unwrap();
+        assert!(!report.passed());
+        assert_eq!(
+            failures(&report),
+            [
+                (
+                    "no placeholders remain",
+                    format!("\"NAME\" in {}", document.display()).as_str()
+                ),
+                ("\"*.pdf\" exists", "no such file"),
+                ("submitted files are at most 1 KB", "sol3.tex (2 KB)"),
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_document() {
+        let dir = TempDir::new("missing_document");
+        let settings = dir.settings(SETTINGS, 2);
+        dir.write("subjects/A/HW3/hw3.pdf", "");
+
+        let report = run_checks(&settings, &dir.path().join("subjects/A"), 3).unwrap();
+        let failures = failures(&report);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].0, "no placeholders remain");
+        assert!(failures[0].1.contains("sol3.tex cannot be read"));
+    }
+}
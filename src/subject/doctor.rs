@@ -0,0 +1,197 @@
+use super::files::update_subject_settings;
+use super::Subject;
+use crate::settings::Settings;
+use std::{
+    env,
+    fmt::{self, Display},
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// The problems found in the settings and in the subjects.
+pub struct DoctorReport {
+    problems: Box<[String]>,
+}
+
+impl DoctorReport {
+    pub fn healthy(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Look for problems in the global settings, in the per-subject settings files, and in the subject directories.
+///
+/// Problems of a subject's settings that are inherited from the global settings are only reported once.
+/// If the format specifiers of the global settings are invalid, the subjects are not checked
+/// (since none of them could be loaded).
+pub fn diagnose(settings: &Settings) -> anyhow::Result<DoctorReport> {
+    let global_problems = settings_problems(settings);
+    let mut problems = global_problems.clone();
+    let formats_valid = match settings.validate_global() {
+        Ok(()) => true,
+        Err(err) => {
+            problems.push(format!("invalid settings: {err:#}"));
+            false
+        }
+    };
+    if !formats_valid || !settings.subjects_dir().is_dir() {
+        return Ok(DoctorReport {
+            problems: problems.into(),
+        });
+    }
+
+    let mut subject_dirs: Vec<_> = settings
+        .subjects_dir()
+        .read_dir()?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect();
+    subject_dirs.sort_unstable();
+
+    for dir in subject_dirs {
+        let name = dir.file_name().unwrap_or_default().to_string_lossy();
+        let subject_settings = match update_subject_settings(settings, &dir) {
+            Ok(subject_settings) => subject_settings,
+            Err(err) => {
+                problems.push(format!("{name}: invalid settings: {err:#}"));
+                continue;
+            }
+        };
+        for problem in settings_problems(&subject_settings) {
+            if !global_problems.contains(&problem) {
+                problems.push(format!("{name}: {problem}"));
+            }
+        }
+        if let Err(err) = Subject::with_settings(subject_settings, dir.clone()) {
+            problems.push(format!("{name}: not a valid subject: {err:#}"));
+        }
+    }
+
+    Ok(DoctorReport {
+        problems: problems.into(),
+    })
+}
+
+/// The problems of the paths and programs referenced by the settings.
+fn settings_problems(settings: &Settings) -> Vec<String> {
+    let mut problems = Vec::new();
+    if !settings.subjects_dir().is_dir() {
+        problems.push(format!(
+            "`subjects_dir` {} is not a directory",
+            settings.subjects_dir().display()
+        ));
+    }
+    if let Some(questions_file_settings) = settings.questions_file_settings() {
+        let downloads_dir = questions_file_settings.downloads_dir();
+        if !downloads_dir.is_dir() {
+            problems.push(format!(
+                "`downloads_dir` {} is not a directory",
+                downloads_dir.display()
+            ));
+        }
+    }
+    let documents = settings.documents();
+    let mut template_files: Vec<_> = documents
+        .iter()
+        .filter_map(|document_settings| document_settings.template_file())
+        .collect();
+    template_files.sort_unstable();
+    template_files.dedup();
+    for template_file in template_files {
+        // opening a directory succeeds on some platforms
+        if template_file.is_dir() {
+            problems.push(format!(
+                "template file {} is a directory",
+                template_file.display()
+            ));
+        } else if let Err(err) = fs::File::open(template_file) {
+            problems.push(format!(
+                "template file {} is unreadable: {err}",
+                template_file.display()
+            ));
+        }
+    }
+
+    let mut binaries = Vec::new();
+    if let Some(questions_file_settings) = settings.questions_file_settings() {
+        binaries.extend(
+            questions_file_settings
+                .open_settings()
+                .map(|open| open.binary()),
+        );
+        if let Some(attachments_settings) = questions_file_settings.attachments_settings() {
+            binaries.extend(
+                attachments_settings
+                    .rules()
+                    .iter()
+                    .filter_map(|rule| rule.open_settings())
+                    .map(|open| open.binary()),
+            );
+        }
+    }
+    binaries.extend(
+        documents
+            .iter()
+            .filter_map(|document_settings| document_settings.open_settings())
+            .map(|open| open.binary()),
+    );
+    binaries.extend(
+        settings
+            .export_settings()
+            .map(|export| export.command().binary()),
+    );
+    binaries.sort_unstable();
+    binaries.dedup();
+    for binary in binaries {
+        if !binary_exists(binary) {
+            problems.push(format!("opener `{binary}` is not found on PATH"));
+        }
+    }
+
+    problems
+}
+
+/// Whether `binary` can be run: either a path to an existing file, or the name of a file in a `PATH` directory.
+/// On Windows, the extensions in `PATHEXT` (e.g. `.exe`, `.cmd`) may be omitted, as when running it.
+fn binary_exists(binary: &str) -> bool {
+    let path = Path::new(binary);
+    if path.components().count() > 1 {
+        return executable_candidates(path).any(|path| path.is_file());
+    }
+    env::var_os("PATH").is_some_and(|paths| {
+        env::split_paths(&paths)
+            .any(|dir| executable_candidates(&dir.join(binary)).any(|path| path.is_file()))
+    })
+}
+
+/// The paths that running `path` may refer to.
+fn executable_candidates(path: &Path) -> impl Iterator<Item = PathBuf> + '_ {
+    let extensions = if cfg!(windows) {
+        env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_owned())
+    } else {
+        String::new()
+    };
+    let with_extensions: Vec<_> = extensions
+        .split(';')
+        .filter(|extension| !extension.is_empty())
+        .map(|extension| {
+            let mut path = path.as_os_str().to_owned();
+            path.push(extension);
+            PathBuf::from(path)
+        })
+        .collect();
+    std::iter::once(path.to_owned()).chain(with_extensions)
+}
+
+impl Display for DoctorReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.healthy() {
+            return writeln!(f, "no problems found");
+        }
+        for problem in self.problems.iter() {
+            writeln!(f, "[PROBLEM] {problem}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,154 @@
+use crate::settings::{attachments_settings::AttachmentsSettings, download_filter::DownloadFilter};
+use std::{
+    fmt::{self, Display},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+/// A file in the downloads directory.
+#[derive(Clone)]
+pub struct Download {
+    path: PathBuf,
+    size: u64,
+    created: SystemTime,
+}
+
+impl Download {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Get the `count` most recent downloads that pass the filter, the most recent first.
+pub fn recent_downloads(
+    downloads_directory: &Path,
+    filter: &DownloadFilter,
+    count: usize,
+) -> anyhow::Result<Box<[Download]>> {
+    let matcher = filter.matcher()?;
+    let mut downloads: Vec<_> = downloads_directory
+        .read_dir()?
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let meta = entry.metadata().ok()?;
+            if !matcher(&entry.path(), &meta) {
+                return None;
+            }
+            let created = meta.created().ok()?;
+            Some(Download {
+                path: entry.path(),
+                size: meta.len(),
+                created,
+            })
+        })
+        .collect();
+    downloads.sort_unstable_by_key(|download| std::cmp::Reverse(download.created));
+    downloads.truncate(count);
+
+    Ok(downloads.into())
+}
+
+pub fn get_most_recent_download(
+    downloads_directory: &Path,
+    filter: &DownloadFilter,
+) -> anyhow::Result<PathBuf> {
+    let most_recent_download = recent_downloads(downloads_directory, filter, 1)?
+        .into_vec()
+        .pop()
+        .ok_or(anyhow::anyhow!(
+            "downloads directory has no files matching the filter"
+        ))?;
+
+    Ok(most_recent_download.path)
+}
+
+/// Number of downloads to offer as attachments, when choosing them interactively without a `count`.
+const DEFAULT_ATTACHMENT_CHOICES: usize = 10;
+
+/// Select the attachments among the recent downloads (other than the questions file),
+/// according to the `count` and `within` limits of the attachments settings.
+///
+/// If `interactive` is set, the result is the list of downloads to offer to the user.
+pub fn select_attachments(
+    downloads_directory: &Path,
+    attachments_settings: &AttachmentsSettings,
+    questions_file: Option<&Path>,
+    interactive: bool,
+) -> anyhow::Result<Box<[Download]>> {
+    let count = match attachments_settings.count() {
+        Some(count) => count,
+        None if interactive => DEFAULT_ATTACHMENT_CHOICES,
+        None if attachments_settings.within().is_some() => usize::MAX,
+        None => return Ok(Box::new([])),
+    };
+
+    let downloads = recent_downloads(downloads_directory, &DownloadFilter::default(), usize::MAX)?;
+    let Some(most_recent) = downloads.first().map(|download| download.created) else {
+        return Ok(Box::new([]));
+    };
+
+    Ok(downloads
+        .into_vec()
+        .into_iter()
+        .filter(|download| Some(download.path.as_path()) != questions_file)
+        .filter(|download| {
+            attachments_settings.within().map_or(true, |within| {
+                most_recent
+                    .duration_since(download.created)
+                    .is_ok_and(|age| age <= within)
+            })
+        })
+        .take(count)
+        .collect())
+}
+
+impl Display for Download {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self
+            .path
+            .file_name()
+            .unwrap_or(self.path.as_os_str())
+            .to_string_lossy();
+        let age = SystemTime::now()
+            .duration_since(self.created)
+            .unwrap_or_default();
+
+        write!(f, "{name} ({}, {} ago)", Size(self.size), Age(age))
+    }
+}
+
+struct Size(u64);
+
+impl Display for Size {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+
+        if self.0 < 1024 {
+            return write!(f, "{} B", self.0);
+        }
+        let mut size = self.0 as f64 / 1024.0;
+        let mut unit = UNITS[0];
+        for next_unit in &UNITS[1..] {
+            if size < 1024.0 {
+                break;
+            }
+            size /= 1024.0;
+            unit = next_unit;
+        }
+        write!(f, "{size:.1} {unit}")
+    }
+}
+
+struct Age(Duration);
+
+impl Display for Age {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = self.0.as_secs();
+        match secs {
+            0..=59 => write!(f, "{secs} seconds"),
+            60..=3599 => write!(f, "{} minutes", secs / 60),
+            3600..=86399 => write!(f, "{} hours", secs / 3600),
+            _ => write!(f, "{} days", secs / 86400),
+        }
+    }
+}
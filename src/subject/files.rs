@@ -1,50 +1,262 @@
+use chrono::Datelike;
 use std::{
-    collections::HashSet,
+    collections::{BTreeMap, HashSet},
+    ffi::OsStr,
     fs,
     path::{Path, PathBuf},
-    process::{self, Command},
 };
 
+use super::archive::{self, ArchiveKind};
+use super::downloads;
+use super::journal::Journal;
+use super::lyx::{LyxDocument, LyxEdit};
+use super::plan::{
+    AttachmentPlan, DocumentPlan, ExportPlan, ExtractPlan, NewHwOptions, NewHwPlan, OpenCommand,
+    OpenPlan, QuestionsFilePlan, ReplacementPlan, TemplateDirPlan,
+};
 use crate::settings::{
+    document_settings::{DocumentEngine, DocumentSettings, ReplacementConfig},
+    export_settings::ExportFiles,
+    format_context::FormatContext,
     open_settings::{Formattable, OpenSettings},
     Settings,
 };
 
 /// Create a new HW folder under the specified subject directory,
 /// and move the most recently downloaded file (from the downloads directory) to there.
-pub fn create_new_hw_dir(settings: &Settings, subject_dir: &Path) -> anyhow::Result<()> {
+pub fn create_new_hw_dir(
+    settings: &Settings,
+    subject_dir: &Path,
+    options: &NewHwOptions,
+) -> anyhow::Result<usize> {
+    let plan = plan_new_hw_dir(settings, subject_dir, options)?;
+    execute_plan(&plan)?;
+    Ok(plan.num)
+}
+
+/// Compute what [`create_new_hw_dir`] would do, without touching the filesystem.
+pub fn plan_new_hw_dir(
+    settings: &Settings,
+    subject_dir: &Path,
+    options: &NewHwOptions,
+) -> anyhow::Result<NewHwPlan> {
     let settings = update_subject_settings(settings, subject_dir)?;
-    let (num, hw_dir) = create_hw_dir(&settings, subject_dir)?;
-    create_questions_file(&settings, num, &hw_dir)?;
-    create_lyx_file(&settings, num, &hw_dir)?;
-    if settings.open_after_creation() {
-        open_hw_dir(&settings, &hw_dir, num)?;
+
+    let num = get_last_hw_num(&settings, subject_dir)? + 1;
+    if settings
+        .max_hw_dirs()
+        .is_some_and(|max_hw_dirs| num > max_hw_dirs)
+    {
+        anyhow::bail!("Maximum number of HW directories reached");
+    }
+    let hw_dir = subject_dir.join(settings.hw_dir(num)?);
+
+    let questions_file = plan_questions_file(&settings, num, &hw_dir, options)?;
+    let (attachments, attachment_open_commands) = plan_attachments(
+        &settings,
+        num,
+        &hw_dir,
+        questions_file.as_ref().map(|plan| plan.src.as_path()),
+        options,
+    )?;
+    let template_dir = plan_template_dir(&settings, num, &hw_dir)?;
+    let documents = settings
+        .documents()
+        .iter()
+        .map(|document_settings| {
+            plan_document(&settings, document_settings, num, subject_dir, &hw_dir)
+        })
+        .collect::<anyhow::Result<_>>()?;
+    let open_commands = if settings.open_after_creation() {
+        let questions_file = questions_file.as_ref().map(|plan| plan.dest.as_path());
+        let mut commands = open_commands(&settings, &hw_dir, num, questions_file)?.into_vec();
+        commands.extend(attachment_open_commands);
+        commands.into()
+    } else {
+        Box::default()
+    };
+
+    Ok(NewHwPlan {
+        num,
+        hw_dir,
+        questions_file,
+        attachments,
+        template_dir,
+        documents,
+        open_commands,
+    })
+}
+
+/// Carry out a plan computed by [`plan_new_hw_dir`].
+///
+/// Creation is all-or-nothing: if any step fails, all previous steps are rolled back.
+pub fn execute_plan(plan: &NewHwPlan) -> anyhow::Result<()> {
+    let mut journal = Journal::new();
+    match create_hw_dir_contents(plan, &mut journal) {
+        Ok(()) => journal.commit()?,
+        Err(err) => {
+            return Err(match journal.rollback() {
+                Ok(()) => anyhow::anyhow!(
+                    "{err:#}\n(rolled back: {} was not created)",
+                    plan.hw_dir.display()
+                ),
+                Err(rollback_err) => anyhow::anyhow!("{err:#}\n{rollback_err}"),
+            });
+        }
+    }
+
+    for command in plan.open_commands.iter() {
+        command.spawn()?;
+    }
+    Ok(())
+}
+
+fn create_hw_dir_contents(plan: &NewHwPlan, journal: &mut Journal) -> anyhow::Result<()> {
+    journal.create_dir(&plan.hw_dir)?;
+    if let Some(questions_file) = &plan.questions_file {
+        create_questions_file(questions_file, journal)?;
+    }
+    for attachment in plan.attachments.iter() {
+        journal.move_file(&attachment.src, &attachment.dest)?;
+    }
+    if let Some(template_dir) = &plan.template_dir {
+        create_template_dir(template_dir, journal)?;
+    }
+    for document in plan.documents.iter() {
+        create_document(document, journal)?;
+    }
+    Ok(())
+}
+
+/// Compute what [`export_hw`] would do, without touching the filesystem.
+///
+/// The exported file is the first document with a `.lyx` filename.
+pub fn plan_export(
+    settings: &Settings,
+    subject_dir: &Path,
+    num: usize,
+) -> anyhow::Result<ExportPlan> {
+    let settings = update_subject_settings(settings, subject_dir)?;
+    let Some(export_settings) = settings.export_settings() else {
+        anyhow::bail!("exporting is not configured (see `export` in the settings)");
+    };
+    let hw_dir = existing_hw_dir(&settings, subject_dir, num)?;
+
+    let context = settings.format_context();
+    let documents = settings.documents();
+    let mut lyx_document = None;
+    for document_settings in documents.iter() {
+        if document_settings.filename(num, &context)?.ends_with(".lyx") {
+            lyx_document = Some(document_settings);
+            break;
+        }
+    }
+    let lyx_document =
+        lyx_document.ok_or_else(|| anyhow::anyhow!("no LyX document is configured"))?;
+    let files = ExportFiles {
+        lyx_file: document_path(&settings, lyx_document, &hw_dir, num)?,
+        output: hw_dir.join(export_settings.submission_filename(num, &context)?),
+    };
+    if !files.lyx_file.is_file() {
+        anyhow::bail!("{} does not exist", files.lyx_file.display());
+    }
+
+    Ok(ExportPlan {
+        command: open_from_settings(export_settings.command(), &files, &context)?,
+        lyx_file: files.lyx_file,
+        output: files.output,
+    })
+}
+
+/// Export the LyX file of a HW (e.g. to PDF), according to a plan computed by [`plan_export`].
+pub fn export_hw(plan: &ExportPlan) -> anyhow::Result<()> {
+    plan.command.run()?;
+    if !plan.output.is_file() {
+        anyhow::bail!(
+            "exporting did not create {} (check `export.command`)",
+            plan.output.display()
+        );
     }
     Ok(())
 }
 
 /// Open the last HW directory in the given subject.
 pub fn open_last_hw_dir(settings: &Settings, subject_dir: &Path) -> anyhow::Result<()> {
+    let num = get_last_hw_num(
+        &update_subject_settings(settings, subject_dir)?,
+        subject_dir,
+    )?;
+    open_existing_hw_dir(settings, subject_dir, num)
+}
+
+/// Open the HW directory with the given number in the given subject.
+pub fn open_existing_hw_dir(
+    settings: &Settings,
+    subject_dir: &Path,
+    num: usize,
+) -> anyhow::Result<()> {
+    open_hw(&plan_open(settings, subject_dir, num)?)
+}
+
+/// Compute what [`open_hw`] would do: the commands that open the files of the HW directory with the given number.
+pub fn plan_open(settings: &Settings, subject_dir: &Path, num: usize) -> anyhow::Result<OpenPlan> {
     let settings = update_subject_settings(settings, subject_dir)?;
-    let num = get_last_hw_num(&settings, subject_dir)?;
-    let hw_dir = subject_dir.join(settings.hw_dir(num)?);
-    open_hw_dir(&settings, &hw_dir, num)?;
+    let hw_dir = existing_hw_dir(&settings, subject_dir, num)?;
+    let questions_file = find_questions_file(&settings, &hw_dir, num)?;
+    let mut commands =
+        open_commands(&settings, &hw_dir, num, questions_file.as_deref())?.into_vec();
+    commands.extend(find_attachment_open_commands(
+        &settings,
+        &hw_dir,
+        num,
+        questions_file.as_deref(),
+    )?);
 
+    Ok(OpenPlan {
+        commands: commands.into(),
+    })
+}
+
+/// Open the files of a HW directory, according to a plan computed by [`plan_open`].
+pub fn open_hw(plan: &OpenPlan) -> anyhow::Result<()> {
+    for command in plan.commands.iter() {
+        command.spawn()?;
+    }
     Ok(())
 }
 
+/// Get the path of the HW directory with the given number, failing if it does not exist.
+///
+/// The directory is found by matching against `hw_dir_format` (like [`list_hw_dirs`]) rather than by formatting it,
+/// since its name may depend on the date it was created on (e.g. `{week}`).
+pub fn existing_hw_dir(
+    settings: &Settings,
+    subject_dir: &Path,
+    num: usize,
+) -> anyhow::Result<PathBuf> {
+    list_hw_dirs(settings, subject_dir)?
+        .iter()
+        .find(|(hw_num, _hw_dir)| *hw_num == num)
+        .map(|(_hw_num, hw_dir)| hw_dir.clone())
+        .ok_or_else(|| anyhow::anyhow!("HW {num} does not exist in {}", subject_dir.display()))
+}
+
 pub fn update_subject_settings(
     settings: &Settings,
     subject_dir: &Path,
 ) -> anyhow::Result<Settings> {
-    let Some(subject_settings_filename) = settings.subject_settings_filename() else {
+    let subject_settings_filename = settings
+        .subject_settings_filename()
+        .map(|filename| subject_dir.join(filename))
+        .filter(|filename| filename.is_file());
+    let Some(subject_settings_filename) = subject_settings_filename else {
+        // the global settings are the effective settings of this subject, so check them as such
+        settings
+            .validate_args()
+            .map_err(|err| anyhow::anyhow!("settings of {}: {err:#}", subject_dir.display()))?;
         return Ok(settings.clone());
     };
-    let subject_settings_filename = subject_dir.join(subject_settings_filename);
-    if !subject_settings_filename.is_file() {
-        return Ok(settings.clone());
-    }
-    settings.update([config::File::from(subject_settings_filename)])
+    settings.update(&subject_settings_filename)
 }
 
 fn list_dir(dir: &Path) -> anyhow::Result<impl Iterator<Item = PathBuf>> {
@@ -55,155 +267,812 @@ fn list_dir(dir: &Path) -> anyhow::Result<impl Iterator<Item = PathBuf>> {
 }
 
 pub fn get_last_hw_num(settings: &Settings, subject_dir: &Path) -> anyhow::Result<usize> {
-    // search for the next HW num
-    let paths: Box<[_]> = list_dir(subject_dir)?.collect();
-    let used_filenames: HashSet<_> = paths
-        .iter()
-        .filter_map(|path| path.file_name().and_then(|s| s.to_str()))
+    Ok(list_hw_dirs(settings, subject_dir)?
+        .last()
+        .map_or(0, |(num, _hw_dir)| *num))
+}
+
+/// List the existing HW directories in the given subject, sorted by HW number.
+pub fn list_hw_dirs(
+    settings: &Settings,
+    subject_dir: &Path,
+) -> anyhow::Result<Box<[(usize, PathBuf)]>> {
+    let matcher = settings.hw_dir_matcher()?;
+
+    let mut hw_dirs: Vec<_> = list_dir(subject_dir)?
+        .filter(|path| path.is_dir())
+        .filter_map(|path| {
+            let num = matcher.match_num(path.file_name()?.to_str()?)?;
+            Some((num, path))
+        })
         .collect();
-    for num in (0..=settings.max_hw_dirs()).rev() {
-        let filename = settings.hw_dir(num)?;
-        if used_filenames.contains(&&*filename) {
-            return Ok(num);
+    hw_dirs.sort_unstable_by_key(|(num, _hw_dir)| *num);
+
+    Ok(hw_dirs.into())
+}
+
+/// List the HW numbers that are missing in the given subject,
+/// i.e. numbers below the last HW number that have no HW directory.
+pub fn missing_hw_nums(settings: &Settings, subject_dir: &Path) -> anyhow::Result<Box<[usize]>> {
+    let hw_dirs = list_hw_dirs(settings, subject_dir)?;
+    let existing: HashSet<_> = hw_dirs.iter().map(|(num, _hw_dir)| *num).collect();
+    let last = hw_dirs.last().map_or(0, |(num, _hw_dir)| *num);
+
+    Ok((1..last).filter(|num| !existing.contains(num)).collect())
+}
+
+fn plan_questions_file(
+    settings: &Settings,
+    num: usize,
+    hw_dir: &Path,
+    options: &NewHwOptions,
+) -> anyhow::Result<Option<QuestionsFilePlan>> {
+    let Some(questions_file_settings) = settings.questions_file_settings() else {
+        return Ok(None);
+    };
+
+    let src = match &options.questions_file {
+        Some(questions_file) => questions_file.clone(),
+        None => downloads::get_most_recent_download(
+            questions_file_settings.downloads_dir(),
+            questions_file_settings.filter(),
+        )?,
+    };
+
+    let archive = questions_file_settings
+        .archive_settings()
+        .and_then(|archive_settings| Some((archive_settings, ArchiveKind::detect(&src)?)));
+    let context = settings.format_context();
+    let Some((archive_settings, (kind, stem))) = archive else {
+        let dest = hw_dir.join(questions_file_settings.questions_filename(num, &src, &context)?);
+        return Ok(Some(QuestionsFilePlan {
+            src,
+            dest,
+            extract: None,
+        }));
+    };
+
+    let dir = match archive_settings.subdir(num, &stem, &context)? {
+        Some(subdir) => hw_dir.join(subdir),
+        None => hw_dir.to_owned(),
+    };
+    let questions_pattern = archive_settings.questions_pattern()?;
+    let questions_file = archive::list_files(&src, kind)?
+        .into_iter()
+        .find(|path| questions_pattern.matches_path(path))
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "no file in {} matches {:?}",
+                src.display(),
+                questions_pattern.as_str()
+            )
+        })?;
+    let dest =
+        hw_dir.join(questions_file_settings.questions_filename(num, &questions_file, &context)?);
+
+    Ok(Some(QuestionsFilePlan {
+        src,
+        dest,
+        extract: Some(ExtractPlan {
+            kind,
+            questions_file: dir.join(questions_file),
+            dir,
+        }),
+    }))
+}
+
+fn create_questions_file(plan: &QuestionsFilePlan, journal: &mut Journal) -> anyhow::Result<()> {
+    let Some(extract) = &plan.extract else {
+        return journal.move_file(&plan.src, &plan.dest);
+    };
+
+    archive::extract(&plan.src, extract.kind, &extract.dir, journal)?;
+    if extract.questions_file != plan.dest {
+        journal.move_file(&extract.questions_file, &plan.dest)?;
+    }
+    journal.remove_file_on_commit(&plan.src);
+    Ok(())
+}
+
+/// Plan moving the attachments into the new HW directory.
+/// Also returns the commands used to open them.
+fn plan_attachments(
+    settings: &Settings,
+    num: usize,
+    hw_dir: &Path,
+    questions_file: Option<&Path>,
+    options: &NewHwOptions,
+) -> anyhow::Result<(Box<[AttachmentPlan]>, Vec<OpenCommand>)> {
+    let attachments_settings = settings
+        .questions_file_settings()
+        .and_then(|questions_file_settings| questions_file_settings.attachments_settings());
+
+    // attachments given explicitly are used even if the settings have no `attachments` block
+    let srcs: Vec<_> = match (&options.attachments, settings.questions_file_settings()) {
+        (Some(attachments), _) => attachments.to_vec(),
+        (None, Some(questions_file_settings)) => match attachments_settings {
+            Some(attachments_settings) => downloads::select_attachments(
+                questions_file_settings.downloads_dir(),
+                attachments_settings,
+                questions_file,
+                false,
+            )?
+            .iter()
+            .map(|download| download.path().to_owned())
+            .collect(),
+            None => Vec::new(),
+        },
+        (None, None) => Vec::new(),
+    };
+
+    let mut attachments = Vec::new();
+    let mut open_commands = Vec::new();
+    let context = settings.format_context();
+    for (i, src) in srcs.into_iter().enumerate() {
+        let rule = match attachments_settings {
+            Some(attachments_settings) => attachments_settings.rule_for(&src)?,
+            None => None,
+        };
+        let dest = hw_dir.join(match rule {
+            Some(rule) => rule.attachment_filename(num, i + 1, &src, &context)?,
+            None => src
+                .file_name()
+                .ok_or(anyhow::anyhow!("attachment has no filename"))?
+                .to_string_lossy()
+                .into_owned(),
+        });
+
+        if let Some(open_settings) = rule.and_then(|rule| rule.open_settings()) {
+            open_commands.push(open_from_settings(open_settings, &dest, &context)?);
         }
+        attachments.push(AttachmentPlan { src, dest });
     }
-    Ok(0)
+
+    Ok((attachments.into(), open_commands))
 }
 
-fn create_hw_dir(settings: &Settings, subject_dir: &Path) -> anyhow::Result<(usize, PathBuf)> {
-    let num = get_last_hw_num(settings, subject_dir)? + 1;
-    if num > settings.max_hw_dirs() {
-        anyhow::bail!("Maximum number of HW directories reached");
+fn plan_document(
+    settings: &Settings,
+    document_settings: &DocumentSettings,
+    num: usize,
+    subject_dir: &Path,
+    dir: &Path,
+) -> anyhow::Result<DocumentPlan> {
+    let dest = dir.join(document_settings.filename(num, &settings.format_context())?);
+    let template = document_settings.template_file().map(Path::to_owned);
+    let Some(template) = template else {
+        return Ok(DocumentPlan {
+            dest,
+            template: None,
+            rendered: false,
+            replacements: Box::default(),
+            lyx_edits: Box::default(),
+            contents: None,
+        });
+    };
+
+    let rendered = document_settings.engine() == DocumentEngine::Jinja;
+    let lyx_edits = plan_lyx_edits(settings, document_settings, num)?;
+    // note that if the template is not copied as-is, the entire template is read to memory
+    let mut contents = if rendered {
+        Some(
+            render_template(
+                &fs::read_to_string(&template)?,
+                &template_context(settings, document_settings, num, subject_dir),
+            )
+            .map_err(|err| anyhow::anyhow!("render {}: {err:#}", template.display()))?,
+        )
+    } else if !document_settings.replacements().is_empty() || !lyx_edits.is_empty() {
+        Some(fs::read_to_string(&template)?)
+    } else {
+        None
+    };
+
+    let replacements = match &contents {
+        Some(data) => plan_replacements(
+            settings,
+            document_settings.replacements(),
+            num,
+            data,
+            &template,
+        )?,
+        None => Box::default(),
+    };
+    if let Some(data) = &mut contents {
+        *data = apply_replacements(data, &replacements);
+
+        // make sure that the resulting LyX file is still well-formed
+        if !lyx_edits.is_empty() || dest.extension().is_some_and(|ext| ext == "lyx") {
+            *data = edit_lyx_file(data, &lyx_edits)
+                .map_err(|err| anyhow::anyhow!("LyX file {}: {err:#}", dest.display()))?;
+        }
     }
 
-    let hw_dir = subject_dir.join(settings.hw_dir(num)?);
-    fs::create_dir(&hw_dir)?;
+    Ok(DocumentPlan {
+        dest,
+        template: Some(template),
+        rendered,
+        replacements,
+        lyx_edits,
+        contents,
+    })
+}
+
+fn plan_lyx_edits(
+    settings: &Settings,
+    document_settings: &DocumentSettings,
+    num: usize,
+) -> anyhow::Result<Box<[LyxEdit]>> {
+    let Some(lyx_edit_settings) = document_settings.lyx_edit_settings() else {
+        return Ok(Box::default());
+    };
+
+    let context = settings.format_context();
+    let mut edits: Vec<_> = lyx_edit_settings
+        .header_fields(num, &context)?
+        .into_iter()
+        .map(|(layout, text)| LyxEdit::SetLayoutText {
+            layout: layout.to_owned(),
+            text,
+        })
+        .collect();
+    if let Some(sections_settings) = lyx_edit_settings.sections() {
+        edits.push(LyxEdit::InsertSections {
+            layout: sections_settings.layout().to_owned(),
+            titles: sections_settings.titles(num, &context)?,
+            marker: sections_settings.marker().map(ToOwned::to_owned),
+        });
+    }
 
-    Ok((num, hw_dir))
+    Ok(edits.into())
 }
 
-fn create_questions_file(settings: &Settings, num: usize, hw_dir: &Path) -> anyhow::Result<()> {
-    let Some(questions_file_settings) = settings.questions_file_settings() else {
-        return Ok(());
+/// Apply the edits to a LyX file, checking that it is well-formed before and after.
+fn edit_lyx_file(data: &str, edits: &[LyxEdit]) -> anyhow::Result<String> {
+    let mut document = LyxDocument::parse(data)?;
+    for edit in edits {
+        document.apply(edit)?;
+    }
+    document.validate()?;
+
+    Ok(document.to_string())
+}
+
+/// The variables available to templates rendered by the template engine.
+fn template_context(
+    settings: &Settings,
+    document_settings: &DocumentSettings,
+    num: usize,
+    subject_dir: &Path,
+) -> BTreeMap<String, serde_json::Value> {
+    // the arguments of format specifiers, then the document's fields
+    let format_context = settings.format_context();
+    let mut context: BTreeMap<_, _> = format_context
+        .args()
+        .iter()
+        .map(|(name, value)| (name.clone(), value.as_str().into()))
+        .chain(
+            document_settings
+                .fields()
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone())),
+        )
+        .collect();
+
+    let subject = subject_dir
+        .file_name()
+        .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+    let now = format_context.now();
+    context.insert("num".into(), num.into());
+    context.insert("subject".into(), subject.into());
+    // numbers, rather than the strings of the format arguments
+    context.insert("year".into(), now.year().into());
+    context.insert("month".into(), now.month().into());
+    context.insert("day".into(), now.day().into());
+    context
+}
+
+fn render_template(
+    template: &str,
+    context: &BTreeMap<String, serde_json::Value>,
+) -> anyhow::Result<String> {
+    let mut env = minijinja::Environment::new();
+    env.set_keep_trailing_newline(true);
+    // fail on typos instead of rendering them as empty strings
+    env.set_undefined_behavior(minijinja::UndefinedBehavior::Strict);
+
+    Ok(env.render_str(template, context)?)
+}
+
+/// Plan copying the template directory (if any) into the new HW directory.
+fn plan_template_dir(
+    settings: &Settings,
+    num: usize,
+    hw_dir: &Path,
+) -> anyhow::Result<Option<TemplateDirPlan>> {
+    let Some(template_dir_settings) = settings.template_dir_settings() else {
+        return Ok(None);
     };
+    let src = template_dir_settings.path();
+    if !src.is_dir() {
+        anyhow::bail!("template directory {} does not exist", src.display());
+    }
 
-    let questions_file_src = get_most_recent_download(questions_file_settings.downloads_dir())?;
-    let questions_file_dest = hw_dir.join(questions_file_settings.questions_filename(num)?);
+    let mut dirs = Vec::new();
+    let mut files = Vec::new();
+    let context = settings.format_context();
+    for relative_path in walk_dir(src)? {
+        let template = src.join(&relative_path);
+        if template.is_dir() {
+            // links to directories are not followed (see `walk_dir`), so they are left out
+            if !fs::symlink_metadata(&template)?.is_symlink() {
+                dirs.push(hw_dir.join(relative_path));
+            }
+            continue;
+        }
 
-    move_file(&questions_file_src, &questions_file_dest)?;
+        if !template_dir_settings.is_formatted(&relative_path)? {
+            files.push(DocumentPlan {
+                dest: hw_dir.join(relative_path),
+                template: Some(template),
+                rendered: false,
+                replacements: Box::default(),
+                lyx_edits: Box::default(),
+                contents: None,
+            });
+            continue;
+        }
 
-    Ok(())
+        let filename = relative_path
+            .file_name()
+            .and_then(OsStr::to_str)
+            .ok_or_else(|| anyhow::anyhow!("invalid filename: {}", template.display()))?;
+        let dest = hw_dir
+            .join(&relative_path)
+            .with_file_name(template_dir_settings.filename(filename, num, &context)?);
+        // note that if there are any replacements, the entire file is read to memory
+        let (replacements, contents) = match template_dir_settings.replacements() {
+            [] => (Box::default(), None),
+            replacements => {
+                let data = fs::read_to_string(&template)?;
+                let replacements =
+                    plan_replacements(settings, replacements, num, &data, &template)?;
+                let contents = apply_replacements(&data, &replacements);
+                (replacements, Some(contents))
+            }
+        };
+        files.push(DocumentPlan {
+            dest,
+            template: Some(template),
+            rendered: false,
+            replacements,
+            lyx_edits: Box::default(),
+            contents,
+        });
+    }
+
+    Ok(Some(TemplateDirPlan {
+        src: src.to_owned(),
+        dirs: dirs.into(),
+        files: files.into(),
+    }))
 }
 
-fn move_file(src: &Path, dest: &Path) -> anyhow::Result<()> {
-    // first of all, try simply renaming the file
-    if let Ok(()) = fs::rename(src, dest) {
-        return Ok(());
+/// List the paths in a directory tree (relative to `dir`), each directory before its contents.
+///
+/// Symbolic links to directories are listed, but not followed, so that they cannot form loops.
+pub fn walk_dir(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    let mut entries: Vec<_> = list_dir(dir)?.collect();
+    entries.sort_unstable();
+    for path in entries {
+        let Some(name) = path.file_name() else {
+            continue;
+        };
+        paths.push(PathBuf::from(name));
+        if fs::symlink_metadata(&path)?.is_dir() {
+            paths.extend(
+                walk_dir(&path)?
+                    .into_iter()
+                    .map(|sub| Path::new(name).join(sub)),
+            );
+        }
     }
+    Ok(paths)
+}
 
-    // if that failed, try copying the file from src to dest and then deleting src
-    fs::copy(src, dest)?;
-    fs::remove_file(src)?;
+/// Plan the replacements in `data`, the contents of (or rendered from) `template`.
+fn plan_replacements(
+    settings: &Settings,
+    replacements: &[ReplacementConfig],
+    num: usize,
+    data: &str,
+    template: &Path,
+) -> anyhow::Result<Box<[ReplacementPlan]>> {
+    let context = settings.format_context();
+    replacements
+        .iter()
+        .map(|replace| {
+            let from = replace.from().to_owned();
+            let regex = replace.regex()?;
+            let to = replace.to(num, &context)?;
+            let matches = regex.find_iter(data).count();
+            if matches == 0 && replace.required() {
+                anyhow::bail!("{from:?} was not found in {}", template.display());
+            }
+            Ok(ReplacementPlan {
+                from,
+                regex,
+                to,
+                expand: replace.expands_captures(),
+                count: replace.count(),
+                matches,
+            })
+        })
+        .collect()
+}
 
+fn create_template_dir(plan: &TemplateDirPlan, journal: &mut Journal) -> anyhow::Result<()> {
+    for dir in plan.dirs.iter() {
+        journal.create_dir(dir)?;
+    }
+    for file in plan.files.iter() {
+        create_document(file, journal)?;
+    }
     Ok(())
 }
 
-fn get_most_recent_download(downloads_directory: &Path) -> anyhow::Result<PathBuf> {
-    let most_recent_download = downloads_directory
-        .read_dir()?
-        .filter_map(Result::ok)
-        .filter_map(|entry| {
-            let meta = entry.metadata().ok()?;
-            let created = meta.created().ok()?;
-            Some((entry, created))
+fn apply_replacements(data: &str, replacements: &[ReplacementPlan]) -> String {
+    let mut data = data.to_owned();
+    for replace in replacements {
+        data = replace.apply(&data);
+    }
+    data
+}
+
+fn create_document(plan: &DocumentPlan, journal: &mut Journal) -> anyhow::Result<()> {
+    match (&plan.contents, &plan.template) {
+        (Some(contents), _) => journal.write_file(&plan.dest, contents),
+        // copy from template file as-is
+        (None, Some(template)) => journal.copy_file(template, &plan.dest),
+        // create a new empty file
+        (None, None) => journal.write_file(&plan.dest, ""),
+    }
+}
+
+/// Find the questions file in an existing HW directory.
+///
+/// The questions filename may depend on the name of the original download,
+/// so every file in the HW directory is checked against the questions filename format.
+/// A format that does not use `num` cannot tell the questions file from other files, so nothing is found with it.
+fn find_questions_file(
+    settings: &Settings,
+    hw_dir: &Path,
+    num: usize,
+) -> anyhow::Result<Option<PathBuf>> {
+    let Some(questions_file_settings) = settings.questions_file_settings() else {
+        return Ok(None);
+    };
+    let matcher = questions_file_settings.questions_filename_matcher()?;
+    if !matcher.uses_arg() {
+        return Ok(None);
+    }
+    let document_filenames = document_filenames(settings, hw_dir, num)?;
+
+    for (file, filename) in list_files(hw_dir)? {
+        if document_filenames.contains(&filename) {
+            continue;
+        }
+        if matcher.matches(&filename, num) {
+            return Ok(Some(file));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Find the attachments in an existing HW directory that have an opener,
+/// and get the commands used to open them.
+fn find_attachment_open_commands(
+    settings: &Settings,
+    hw_dir: &Path,
+    num: usize,
+    questions_file: Option<&Path>,
+) -> anyhow::Result<Vec<OpenCommand>> {
+    let Some(attachments_settings) = settings
+        .questions_file_settings()
+        .and_then(|questions_file_settings| questions_file_settings.attachments_settings())
+    else {
+        return Ok(Vec::new());
+    };
+    let document_filenames = document_filenames(settings, hw_dir, num)?;
+    let files: Vec<_> = list_files(hw_dir)?
+        .filter(|(file, filename)| {
+            Some(file.as_path()) != questions_file && !document_filenames.contains(filename)
         })
-        .max_by_key(|(_entry, created)| *created)
-        .ok_or(anyhow::anyhow!("downloads directory was empty"))?
-        .0;
-
-    Ok(most_recent_download.path())
-}
-
-fn create_lyx_file(settings: &Settings, num: usize, dir: &Path) -> anyhow::Result<()> {
-    let Some(lyx_file_settings) = settings.lyx_file_settings() else {
-        return Ok(());
-    };
-
-    let lyx_file = dir.join(lyx_file_settings.lyx_filename(num)?);
-
-    if let Some(lyx_template) = lyx_file_settings.lyx_template_file() {
-        if lyx_file_settings.replacements().is_empty() {
-            // copy from LyX template file. no replacements.
-            fs::copy(lyx_template, lyx_file)?;
-        } else {
-            // copy from LyX template file with replacements.
-            let mut data = fs::read_to_string(lyx_template)?;
-            for replace in lyx_file_settings.replacements() {
-                let from = replace.from();
-                let to = replace.to(num, settings.hebrew_name())?;
-                data = if let Some(count) = replace.count() {
-                    data.replacen(from, &to, count)
-                } else {
-                    data.replace(from, &to)
-                }
+        .collect();
+
+    let mut commands = Vec::new();
+    let context = settings.format_context();
+    for (file, filename) in files.iter() {
+        // like when the attachment was moved in, the first rule that applies to the file is used
+        let mut rule = None;
+        for candidate in attachments_settings.rules() {
+            if candidate.matches_attachment(filename, num)? {
+                rule = Some(candidate);
+                break;
             }
-            fs::write(lyx_file, data)?;
         }
-    } else {
-        // create a new empty file
-        fs::File::create(lyx_file)?;
+        if let Some(open_settings) = rule.and_then(|rule| rule.open_settings()) {
+            commands.push(open_from_settings(open_settings, file, &context)?);
+        }
     }
 
-    Ok(())
+    Ok(commands)
 }
 
-fn open_hw_dir(settings: &Settings, hw_dir: &Path, num: usize) -> anyhow::Result<()> {
-    open_questions_file(settings, hw_dir, num)?;
-    open_lyx_file(settings, hw_dir, num)?;
+/// The filenames of the documents in the HW directory with the given number.
+fn document_filenames(
+    settings: &Settings,
+    hw_dir: &Path,
+    num: usize,
+) -> anyhow::Result<HashSet<String>> {
+    settings
+        .documents()
+        .iter()
+        .map(|document_settings| {
+            let document = document_path(settings, document_settings, hw_dir, num)?;
+            Ok(document
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned())
+        })
+        .collect()
+}
 
-    Ok(())
+/// The path of a document in the HW directory with the given number.
+///
+/// The filename format may use the date, so a document created on another day is found by matching
+/// the format against the files in the HW directory, when there is no file named as it would be today.
+pub(super) fn document_path(
+    settings: &Settings,
+    document_settings: &DocumentSettings,
+    hw_dir: &Path,
+    num: usize,
+) -> anyhow::Result<PathBuf> {
+    let document = hw_dir.join(document_settings.filename(num, &settings.format_context())?);
+    if document.exists() || !hw_dir.is_dir() {
+        return Ok(document);
+    }
+
+    let matcher = document_settings.filename_matcher()?;
+    Ok(list_files(hw_dir)?
+        .find(|(_file, filename)| matcher.matches(filename, num))
+        .map_or(document, |(file, _filename)| file))
+}
+
+/// List the files (not directories) in the given directory, with their names, sorted by name.
+fn list_files(dir: &Path) -> anyhow::Result<impl Iterator<Item = (PathBuf, String)>> {
+    let mut files: Vec<_> = list_dir(dir)?
+        .filter(|path| path.is_file())
+        .filter_map(|path| {
+            let filename = path.file_name()?.to_str()?.to_owned();
+            Some((path, filename))
+        })
+        .collect();
+    files.sort_unstable_by(|(_file1, filename1), (_file2, filename2)| filename1.cmp(filename2));
+
+    Ok(files.into_iter())
+}
+
+/// The commands used to open the files in the given HW directory.
+fn open_commands(
+    settings: &Settings,
+    hw_dir: &Path,
+    num: usize,
+    questions_file: Option<&Path>,
+) -> anyhow::Result<Box<[OpenCommand]>> {
+    let mut commands = Vec::new();
+    if let Some(questions_file) = questions_file {
+        commands.extend(open_questions_file(settings, questions_file)?);
+    }
+    for document_settings in settings.documents().iter() {
+        commands.extend(open_document(settings, document_settings, hw_dir, num)?);
+    }
+
+    Ok(commands.into())
 }
 
 fn open_from_settings<T: Formattable>(
     open_settings: &OpenSettings<T>,
     params: &T::Params,
-) -> anyhow::Result<()> {
-    Command::new(open_settings.binary())
-        .args(open_settings.args(params)?)
-        .stdin(process::Stdio::null())
-        .stdout(process::Stdio::null())
-        .stderr(process::Stdio::null())
-        .spawn()
-        .map(|_child| ()) // ignore child process
-        .map_err(Into::into)
+    context: &FormatContext,
+) -> anyhow::Result<OpenCommand> {
+    Ok(OpenCommand {
+        binary: open_settings.binary().to_owned(),
+        args: open_settings.args(params, context)?.collect(),
+    })
 }
 
-fn open_questions_file(settings: &Settings, hw_dir: &Path, num: usize) -> anyhow::Result<()> {
+fn open_questions_file(
+    settings: &Settings,
+    questions_file: &Path,
+) -> anyhow::Result<Option<OpenCommand>> {
     let Some(questions_file_settings) = settings.questions_file_settings() else {
-        return Ok(());
+        return Ok(None);
     };
     let Some(open_settings) = questions_file_settings.open_settings() else {
-        return Ok(());
+        return Ok(None);
     };
 
-    let questions_file = hw_dir.join(questions_file_settings.questions_filename(num)?);
+    open_from_settings(open_settings, questions_file, &settings.format_context()).map(Some)
+}
 
-    open_from_settings(open_settings, &questions_file)?;
+fn open_document(
+    settings: &Settings,
+    document_settings: &DocumentSettings,
+    hw_dir: &Path,
+    num: usize,
+) -> anyhow::Result<Option<OpenCommand>> {
+    let Some(open_settings) = document_settings.open_settings() else {
+        return Ok(None);
+    };
 
-    Ok(())
+    let document = document_path(settings, document_settings, hw_dir, num)?;
+
+    open_from_settings(open_settings, &document, &settings.format_context()).map(Some)
 }
 
-fn open_lyx_file(settings: &Settings, hw_dir: &Path, num: usize) -> anyhow::Result<()> {
-    let Some(lyx_file_settings) = settings.lyx_file_settings() else {
-        return Ok(());
-    };
-    let Some(open_settings) = lyx_file_settings.open_settings() else {
-        return Ok(());
-    };
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
 
-    let lyx_filename = hw_dir.join(lyx_file_settings.lyx_filename(num)?);
+    #[test]
+    fn document_created_on_another_day() {
+        let dir = TempDir::new("document_created_on_another_day");
+        let extra = r#", "documents": [ { "filename_format": "sol{num}_{date}.tex" } ]"#;
+        let hw_dir = dir.path().join("subjects/A/HW3");
+        dir.write("subjects/A/HW3/sol3_2026-11-02.tex", "");
+        dir.write("subjects/A/HW3/sol30_2026-11-02.tex", "");
 
-    open_from_settings(open_settings, &lyx_filename)?;
+        let settings = dir.settings(extra, 9);
+        let document_settings = &settings.documents()[0];
+        assert_eq!(
+            document_path(&settings, document_settings, &hw_dir, 3).unwrap(),
+            hw_dir.join("sol3_2026-11-02.tex")
+        );
+        // no document of HW4 exists yet, so it is named by today's date
+        assert_eq!(
+            document_path(&settings, document_settings, &hw_dir, 4).unwrap(),
+            hw_dir.join("sol4_2026-11-09.tex")
+        );
+    }
 
-    Ok(())
+    #[test]
+    fn renamed_attachments() {
+        let dir = TempDir::new("renamed_attachments");
+        let extra = format!(
+            r#", "questions_file": {{
+                "downloads_dir": {:?},
+                "questions_filename_format": "q{{num}}.pdf",
+                "attachments": {{ "rules": [
+                    {{ "name_glob": "*.py", "filename_format": "{{stem}}_{{num}}.{{ext}}", "open": {{ "binary": "py", "args_format": [] }} }},
+                    {{ "name_glob": "hw*.zip", "filename_format": "data_{{num}}.zip", "open": {{ "binary": "zip", "args_format": [] }} }}
+                ] }}
+            }}"#,
+            dir.path()
+        );
+        let settings = dir.settings(&extra, 2);
+        let hw_dir = dir.path().join("subjects/A/HW3");
+        dir.write("subjects/A/HW3/q3.pdf", "");
+        dir.write("subjects/A/HW3/data_3.zip", "");
+        dir.write("subjects/A/HW3/my_skel_3.py", "");
+
+        let questions_file = find_questions_file(&settings, &hw_dir, 3).unwrap();
+        assert_eq!(questions_file, Some(hw_dir.join("q3.pdf")));
+        let commands =
+            find_attachment_open_commands(&settings, &hw_dir, 3, questions_file.as_deref())
+                .unwrap();
+        let binaries: Vec<_> = commands
+            .iter()
+            .map(|command| command.binary.as_str())
+            .collect();
+        assert_eq!(binaries, ["zip", "py"]);
+    }
+
+    /// Apply the replacements described by `replacements` (as JSON) to `data`.
+    fn replace(replacements: &str, data: &str) -> String {
+        let dir = TempDir::new("replace");
+        let settings = dir.settings(r#", "variables": { "course": "104031" }"#, 2);
+        let replacements: Box<[ReplacementConfig]> = serde_json::from_str(replacements).unwrap();
+        let plans = plan_replacements(&settings, &replacements, 3, data, Path::new("t")).unwrap();
+        plans
+            .iter()
+            .fold(data.to_owned(), |data, plan| plan.apply(&data))
+    }
+
+    #[test]
+    fn replacement_count() {
+        let data = "X X X";
+        let with_count = |count: &str| {
+            replace(
+                &format!(r#"[{{ "from": "X", "to_format": "{{num}}" {count} }}]"#),
+                data,
+            )
+        };
+        assert_eq!(with_count(""), "3 3 3");
+        assert_eq!(with_count(r#", "count": 2"#), "3 3 X");
+        assert_eq!(with_count(r#", "count": 0"#), "X X X");
+    }
+
+    #[test]
+    fn replacement_regex() {
+        let replacements = r#"[{ "from": "(?P<name>[a-z]+)=0+", "to_format": "${{name}}={course}", "mode": "regex" }]"#;
+        assert_eq!(
+            replace(replacements, "a=00 b=1 c=0"),
+            "a=104031 b=1 c=104031"
+        );
+        // in literal mode, `$` is not special in `to_format`
+        let replacements = r#"[{ "from": "PRICE", "to_format": "$1" }]"#;
+        assert_eq!(replace(replacements, "PRICE"), "$1");
+    }
+
+    #[test]
+    fn replacement_whole_word() {
+        let replacements = r#"[{ "from": "HW", "to_format": "HW{num}", "whole_word": true }]"#;
+        assert_eq!(replace(replacements, "HW HWs (HW)"), "HW3 HWs (HW3)");
+    }
+
+    #[test]
+    fn replacement_anchor() {
+        let data = "X X\nX\nX X";
+        let with_anchor = |anchor: &str| {
+            replace(
+                &format!(r#"[{{ "from": "X", "to_format": "_", "anchor": "{anchor}" }}]"#),
+                data,
+            )
+        };
+        assert_eq!(with_anchor("none"), "_ _\n_\n_ _");
+        assert_eq!(with_anchor("line_start"), "_ X\n_\n_ X");
+        assert_eq!(with_anchor("line_end"), "X _\n_\nX _");
+        assert_eq!(with_anchor("line"), "X X\n_\nX X");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn template_dir() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let dir = TempDir::new("template_dir");
+        let template_dir = dir.path().join("template");
+        let settings = dir.settings(
+            &format!(r#", "template_dir": {{ "path": {template_dir:?} }}"#),
+            2,
+        );
+        let script = dir.write("template/scripts/run.sh", "#!/bin/sh\n");
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        dir.write("template/data.txt", "data");
+        symlink(&template_dir, template_dir.join("scripts/loop")).unwrap();
+        symlink(template_dir.join("data.txt"), template_dir.join("link.txt")).unwrap();
+
+        let hw_dir = dir.path().join("subjects/A/HW3");
+        let plan = plan_template_dir(&settings, 3, &hw_dir).unwrap().unwrap();
+        let mut journal = Journal::new();
+        journal.create_dir_all(&hw_dir).unwrap();
+        create_template_dir(&plan, &mut journal).unwrap();
+        journal.commit().unwrap();
+
+        assert_eq!(
+            walk_dir(&hw_dir).unwrap(),
+            ["data.txt", "link.txt", "scripts", "scripts/run.sh"].map(PathBuf::from)
+        );
+        let mode = fs::metadata(hw_dir.join("scripts/run.sh"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o755);
+        assert_eq!(fs::read_to_string(hw_dir.join("link.txt")).unwrap(), "data");
+    }
 }
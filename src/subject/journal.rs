@@ -0,0 +1,228 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// A record of the filesystem operations performed while creating a HW directory.
+///
+/// Every operation is performed through the journal, so that if a later step fails,
+/// all previous steps can be undone (in reverse order) using [`Journal::rollback`].
+#[derive(Default)]
+pub struct Journal {
+    steps: Vec<Step>,
+}
+
+enum Step {
+    CreateDir(PathBuf),
+    CreateFile(PathBuf),
+    MoveFile {
+        src: PathBuf,
+        dest: PathBuf,
+    },
+    /// Removing a file is deferred until the journal is committed, so it never has to be undone.
+    RemoveFileOnCommit(PathBuf),
+}
+
+impl Journal {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn create_dir(&mut self, path: &Path) -> anyhow::Result<()> {
+        fs::create_dir(path)
+            .map_err(|err| anyhow::anyhow!("create directory {}: {err}", path.display()))?;
+        self.steps.push(Step::CreateDir(path.to_owned()));
+        Ok(())
+    }
+
+    /// Create a directory and all of its missing ancestors.
+    pub fn create_dir_all(&mut self, path: &Path) -> anyhow::Result<()> {
+        if path.as_os_str().is_empty() || path.is_dir() {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            self.create_dir_all(parent)?;
+        }
+        self.create_dir(path)
+    }
+
+    /// Create a new file with the given contents.
+    /// Fails if the file already exists, so that rolling back never deletes a file we did not create.
+    pub fn write_file(&mut self, path: &Path, contents: impl AsRef<[u8]>) -> anyhow::Result<()> {
+        use std::io::Write;
+
+        let mut file = fs::File::create_new(path)
+            .map_err(|err| anyhow::anyhow!("create file {}: {err}", path.display()))?;
+        self.steps.push(Step::CreateFile(path.to_owned()));
+        file.write_all(contents.as_ref())
+            .map_err(|err| anyhow::anyhow!("write file {}: {err}", path.display()))?;
+        Ok(())
+    }
+
+    /// Copy `src` (along with its permissions) into the new file `dest`.
+    pub fn copy_file(&mut self, src: &Path, dest: &Path) -> anyhow::Result<()> {
+        let mut src_file = fs::File::open(src)
+            .map_err(|err| anyhow::anyhow!("open file {}: {err}", src.display()))?;
+        let mut file = fs::File::create_new(dest)
+            .map_err(|err| anyhow::anyhow!("create file {}: {err}", dest.display()))?;
+        self.steps.push(Step::CreateFile(dest.to_owned()));
+        std::io::copy(&mut src_file, &mut file).map_err(|err| {
+            anyhow::anyhow!("copy {} to {}: {err}", src.display(), dest.display())
+        })?;
+        // keep e.g. scripts executable
+        let permissions = src_file
+            .metadata()
+            .map_err(|err| anyhow::anyhow!("read metadata of {}: {err}", src.display()))?
+            .permissions();
+        file.set_permissions(permissions)
+            .map_err(|err| anyhow::anyhow!("set permissions of {}: {err}", dest.display()))?;
+        Ok(())
+    }
+
+    pub fn move_file(&mut self, src: &Path, dest: &Path) -> anyhow::Result<()> {
+        if dest.exists() {
+            anyhow::bail!("{} already exists", dest.display());
+        }
+        move_file(src, dest).map_err(|err| {
+            anyhow::anyhow!("move {} to {}: {err}", src.display(), dest.display())
+        })?;
+        self.steps.push(Step::MoveFile {
+            src: src.to_owned(),
+            dest: dest.to_owned(),
+        });
+        Ok(())
+    }
+
+    /// Remove `path` once the journal is committed.
+    pub fn remove_file_on_commit(&mut self, path: &Path) {
+        self.steps.push(Step::RemoveFileOnCommit(path.to_owned()));
+    }
+
+    /// Forget all recorded steps, keeping their effects, and perform the deferred removals.
+    pub fn commit(self) -> anyhow::Result<()> {
+        for step in self.steps {
+            if let Step::RemoveFileOnCommit(path) = step {
+                fs::remove_file(&path)
+                    .map_err(|err| anyhow::anyhow!("remove file {}: {err}", path.display()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Undo all recorded steps in reverse order.
+    ///
+    /// Rolling back continues even if some step cannot be undone,
+    /// in which case an error describing every such step is returned.
+    pub fn rollback(self) -> anyhow::Result<()> {
+        let errors: Vec<_> = self
+            .steps
+            .into_iter()
+            .rev()
+            .filter_map(|step| step.undo().err())
+            .collect();
+
+        if errors.is_empty() {
+            return Ok(());
+        }
+        anyhow::bail!(
+            "failed to roll back:\n{}",
+            errors
+                .iter()
+                .map(|err| format!("\t{err}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        )
+    }
+}
+
+impl Step {
+    fn undo(self) -> anyhow::Result<()> {
+        match self {
+            Step::CreateDir(path) => fs::remove_dir(&path)
+                .map_err(|err| anyhow::anyhow!("remove directory {}: {err}", path.display())),
+            Step::CreateFile(path) => fs::remove_file(&path)
+                .map_err(|err| anyhow::anyhow!("remove file {}: {err}", path.display())),
+            Step::MoveFile { src, dest } => move_file(&dest, &src).map_err(|err| {
+                anyhow::anyhow!("move {} back to {}: {err}", dest.display(), src.display())
+            }),
+            Step::RemoveFileOnCommit(_) => Ok(()),
+        }
+    }
+}
+
+fn move_file(src: &Path, dest: &Path) -> anyhow::Result<()> {
+    // first of all, try simply renaming the file
+    if let Ok(()) = fs::rename(src, dest) {
+        return Ok(());
+    }
+
+    // if that failed, try copying the file from src to dest and then deleting src
+    fs::copy(src, dest)?;
+    fs::remove_file(src)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    #[test]
+    fn rollback() {
+        let dir = TempDir::new("journal_rollback");
+        let download = dir.write("downloads/q.pdf", "questions");
+        let old_file = dir.write("downloads/old.zip", "");
+        let hw_dir = dir.path().join("HW1");
+
+        let mut journal = Journal::new();
+        journal.create_dir_all(&hw_dir.join("figures")).unwrap();
+        journal
+            .write_file(&hw_dir.join("sol.tex"), "solution")
+            .unwrap();
+        journal
+            .copy_file(&download, &hw_dir.join("figures/copy.pdf"))
+            .unwrap();
+        journal
+            .move_file(&download, &hw_dir.join("q1.pdf"))
+            .unwrap();
+        journal.remove_file_on_commit(&old_file);
+        assert!(hw_dir.join("q1.pdf").is_file());
+        assert!(!download.exists());
+
+        journal.rollback().unwrap();
+        assert!(!hw_dir.exists());
+        assert_eq!(fs::read_to_string(&download).unwrap(), "questions");
+        assert!(old_file.exists());
+    }
+
+    #[test]
+    fn existing_files_are_kept() {
+        let dir = TempDir::new("journal_existing_files");
+        let existing = dir.write("HW1/sol.tex", "mine");
+
+        let mut journal = Journal::new();
+        assert!(journal.write_file(&existing, "new").is_err());
+        journal.rollback().unwrap();
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "mine");
+    }
+
+    #[test]
+    fn failed_rollback() {
+        let dir = TempDir::new("journal_failed_rollback");
+        let hw_dir = dir.path().join("HW1");
+
+        let mut journal = Journal::new();
+        journal.create_dir(&hw_dir).unwrap();
+        journal.write_file(&hw_dir.join("sol.tex"), "").unwrap();
+        // neither step can be undone: the file is gone, and another file keeps the directory from being removed
+        dir.write("HW1/other.tex", "");
+        fs::remove_file(hw_dir.join("sol.tex")).unwrap();
+
+        let err = journal.rollback().unwrap_err().to_string();
+        assert!(err.starts_with("failed to roll back:"));
+        assert!(err.contains("remove file"));
+        assert!(err.contains("remove directory"));
+        assert!(hw_dir.join("other.tex").exists());
+    }
+}
@@ -0,0 +1,379 @@
+use std::fmt::{self, Display};
+
+/// A LyX document, as a list of lines.
+///
+/// Only the structure needed for editing is understood: the `\begin_X`/`\end_X` blocks
+/// (e.g. layouts and insets), which are checked to be balanced when parsing.
+pub struct LyxDocument {
+    lines: Vec<String>,
+    trailing_newline: bool,
+}
+
+/// An edit to apply to a LyX document.
+pub enum LyxEdit {
+    /// Set the text of the first paragraph with the given layout (e.g. `Title`),
+    /// inserting such a paragraph at the beginning of the body if there is none.
+    SetLayoutText { layout: String, text: String },
+    /// Insert a paragraph with the given layout (e.g. `Section`) for each title,
+    /// in place of the paragraph containing `marker`, or at the end of the body.
+    InsertSections {
+        layout: String,
+        titles: Box<[String]>,
+        marker: Option<String>,
+    },
+}
+
+/// Layouts that make up the title block at the beginning of the body, in order.
+const TITLE_LAYOUTS: [&str; 3] = ["Title", "Author", "Date"];
+
+impl LyxDocument {
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let document = Self {
+            lines: text.lines().map(ToOwned::to_owned).collect(),
+            trailing_newline: text.ends_with('\n'),
+        };
+        document.validate()?;
+        Ok(document)
+    }
+
+    /// Check that every `\begin_X` has a matching `\end_X`.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let mut open: Vec<(&str, usize)> = Vec::new();
+        for (i, line) in self.lines.iter().enumerate() {
+            // the preamble and local layout are raw text, only their end matters
+            if let Some(&(kind @ ("preamble" | "local_layout"), _)) = open.last() {
+                if block_end(line) != Some(kind) {
+                    continue;
+                }
+            }
+
+            if let Some(kind) = block_begin(line) {
+                open.push((kind, i));
+            } else if let Some(kind) = block_end(line) {
+                match open.pop() {
+                    Some((begin_kind, _)) if begin_kind == kind => {}
+                    Some((begin_kind, begin)) => anyhow::bail!(
+                        "line {}: \\end_{kind} does not match \\begin_{begin_kind} on line {}",
+                        i + 1,
+                        begin + 1,
+                    ),
+                    None => anyhow::bail!("line {}: unexpected \\end_{kind}", i + 1),
+                }
+            }
+        }
+
+        if let Some((kind, begin)) = open.pop() {
+            anyhow::bail!("line {}: \\begin_{kind} is never closed", begin + 1);
+        }
+        Ok(())
+    }
+
+    pub fn apply(&mut self, edit: &LyxEdit) -> anyhow::Result<()> {
+        match edit {
+            LyxEdit::SetLayoutText { layout, text } => self.set_layout_text(layout, text),
+            LyxEdit::InsertSections {
+                layout,
+                titles,
+                marker,
+            } => self.insert_sections(layout, titles, marker.as_deref()),
+        }
+    }
+
+    fn set_layout_text(&mut self, layout: &str, text: &str) -> anyhow::Result<()> {
+        let paragraphs = self.body_paragraphs()?;
+        if let Some(&(begin, end)) = paragraphs
+            .iter()
+            .find(|&&(begin, _)| layout_name(&self.lines[begin]) == Some(layout))
+        {
+            self.lines.splice(begin + 1..end, escape(text));
+            return Ok(());
+        }
+
+        // insert after the title block, keeping it in order
+        let position = TITLE_LAYOUTS
+            .iter()
+            .position(|title_layout| *title_layout == layout);
+        let at = paragraphs
+            .iter()
+            .take_while(|&&(begin, _)| {
+                let index = TITLE_LAYOUTS
+                    .iter()
+                    .position(|title_layout| layout_name(&self.lines[begin]) == Some(title_layout));
+                index.is_some() && index < position
+            })
+            .last()
+            .map_or(self.body_begin()? + 1, |&(_, end)| end + 1);
+        self.lines.splice(
+            at..at,
+            std::iter::once(String::new()).chain(paragraph(layout, text)),
+        );
+        Ok(())
+    }
+
+    fn insert_sections(
+        &mut self,
+        layout: &str,
+        titles: &[String],
+        marker: Option<&str>,
+    ) -> anyhow::Result<()> {
+        let sections: Vec<_> = titles
+            .iter()
+            .flat_map(|title| {
+                paragraph(layout, title)
+                    .chain([String::new()])
+                    .chain(paragraph("Standard", ""))
+                    .chain([String::new()])
+            })
+            .collect();
+
+        let Some(marker) = marker else {
+            let at = self.body_end()?;
+            self.lines.splice(at..at, sections);
+            return Ok(());
+        };
+        let (begin, end) = self
+            .body_paragraphs()?
+            .into_iter()
+            .find(|&(begin, end)| {
+                self.lines[begin..end]
+                    .iter()
+                    .any(|line| line.contains(marker))
+            })
+            .ok_or_else(|| anyhow::anyhow!("marker {marker:?} was not found"))?;
+        // also remove the blank line after the marker paragraph
+        let end = if self.lines.get(end + 1).is_some_and(|line| line.is_empty()) {
+            end + 1
+        } else {
+            end
+        };
+        self.lines.splice(begin..=end, sections);
+        Ok(())
+    }
+
+    fn body_begin(&self) -> anyhow::Result<usize> {
+        self.lines
+            .iter()
+            .position(|line| line == "\\begin_body")
+            .ok_or_else(|| anyhow::anyhow!("no \\begin_body"))
+    }
+
+    fn body_end(&self) -> anyhow::Result<usize> {
+        self.lines
+            .iter()
+            .rposition(|line| line == "\\end_body")
+            .ok_or_else(|| anyhow::anyhow!("no \\end_body"))
+    }
+
+    /// The line ranges (`\begin_layout` to `\end_layout`, inclusive) of the top-level paragraphs of the body.
+    fn body_paragraphs(&self) -> anyhow::Result<Vec<(usize, usize)>> {
+        let mut paragraphs = Vec::new();
+        let mut depth = 0;
+        let mut begin = 0;
+        for i in self.body_begin()? + 1..self.body_end()? {
+            let line = &self.lines[i];
+            if block_begin(line).is_some() {
+                if depth == 0 && block_begin(line) == Some("layout") {
+                    begin = i;
+                }
+                depth += 1;
+            } else if block_end(line).is_some() {
+                depth -= 1;
+                if depth == 0 && block_end(line) == Some("layout") {
+                    paragraphs.push((begin, i));
+                }
+            }
+        }
+        Ok(paragraphs)
+    }
+}
+
+impl Display for LyxDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.lines.join("\n"))?;
+        if self.trailing_newline {
+            f.write_str("\n")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for LyxEdit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LyxEdit::SetLayoutText { layout, text } => write!(f, "Set {layout}: {text:?}"),
+            LyxEdit::InsertSections {
+                layout,
+                titles,
+                marker,
+            } => {
+                write!(f, "Insert {} {layout} paragraphs", titles.len())?;
+                match marker {
+                    Some(marker) => write!(f, " at {marker:?}"),
+                    None => write!(f, " at the end"),
+                }
+            }
+        }
+    }
+}
+
+/// `X` if the line is `\begin_X ...`.
+fn block_begin(line: &str) -> Option<&str> {
+    let rest = line.strip_prefix("\\begin_")?;
+    rest.split_whitespace().next()
+}
+
+/// `X` if the line is `\end_X`.
+fn block_end(line: &str) -> Option<&str> {
+    let rest = line.strip_prefix("\\end_")?;
+    rest.split_whitespace().next()
+}
+
+/// `X` if the line is `\begin_layout X`.
+fn layout_name(line: &str) -> Option<&str> {
+    line.strip_prefix("\\begin_layout ").map(str::trim)
+}
+
+/// A paragraph with the given layout and text.
+fn paragraph(layout: &str, text: &str) -> impl Iterator<Item = String> {
+    [format!("\\begin_layout {layout}")]
+        .into_iter()
+        .chain(escape(text))
+        .chain(["\\end_layout".to_owned()])
+}
+
+/// Text as lines of a LyX paragraph.
+fn escape(text: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    for (i, part) in text.replace('\n', " ").split('\\').enumerate() {
+        if i > 0 {
+            lines.push("\\backslash".to_owned());
+        }
+        if !part.is_empty() {
+            lines.push(part.to_owned());
+        }
+    }
+    if lines.is_empty() {
+        lines.push(String::new());
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOCUMENT: &str = "\
+\\begin_document
+\\begin_header
+\\begin_preamble
+\\end_layout
+\\end_preamble
+\\end_header
+\\begin_body
+
+\\begin_layout Title
+Old title
+\\end_layout
+
+\\begin_layout Date
+old date
+\\end_layout
+
+\\begin_layout Standard
+\\begin_inset Note Note
+status open
+
+\\begin_layout Plain Layout
+SECTIONS
+\\end_layout
+
+\\end_inset
+\\end_layout
+
+\\begin_layout Standard
+End
+\\end_layout
+
+\\end_body
+\\end_document
+";
+
+    fn edited(edit: LyxEdit) -> String {
+        let mut document = LyxDocument::parse(DOCUMENT).unwrap();
+        document.apply(&edit).unwrap();
+        document.to_string()
+    }
+
+    #[test]
+    fn round_trip() {
+        assert_eq!(LyxDocument::parse(DOCUMENT).unwrap().to_string(), DOCUMENT);
+    }
+
+    #[test]
+    fn unclosed_block() {
+        let err = LyxDocument::parse("\\begin_document\n\\begin_body\n\\end_document\n")
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "line 3: \\end_document does not match \\begin_body on line 2"
+        );
+        let err = LyxDocument::parse("\\begin_document\n").err().unwrap();
+        assert_eq!(err.to_string(), "line 1: \\begin_document is never closed");
+    }
+
+    #[test]
+    fn unexpected_end() {
+        let err = LyxDocument::parse("\\end_layout\n").err().unwrap();
+        assert_eq!(err.to_string(), "line 1: unexpected \\end_layout");
+    }
+
+    #[test]
+    fn set_existing_layout() {
+        let text = edited(LyxEdit::SetLayoutText {
+            layout: "Title".to_owned(),
+            text: "HW 3 \\ Calculus".to_owned(),
+        });
+        assert!(text.contains("\\begin_layout Title\nHW 3 \n\\backslash\n Calculus\n\\end_layout"));
+        assert!(!text.contains("Old title"));
+        LyxDocument::parse(&text).unwrap();
+    }
+
+    #[test]
+    fn insert_missing_layout_in_order() {
+        let text = edited(LyxEdit::SetLayoutText {
+            layout: "Author".to_owned(),
+            text: "Student".to_owned(),
+        });
+        let title = text.find("Old title").unwrap();
+        let author = text.find("Student").unwrap();
+        let date = text.find("old date").unwrap();
+        assert!(title < author && author < date);
+        LyxDocument::parse(&text).unwrap();
+    }
+
+    #[test]
+    fn insert_sections_at_marker() {
+        let text = edited(LyxEdit::InsertSections {
+            layout: "Section".to_owned(),
+            titles: Box::new(["Question 1".to_owned(), "Question 2".to_owned()]),
+            marker: Some("SECTIONS".to_owned()),
+        });
+        assert!(!text.contains("SECTIONS"));
+        let first = text.find("\\begin_layout Section\nQuestion 1").unwrap();
+        let second = text.find("\\begin_layout Section\nQuestion 2").unwrap();
+        assert!(first < second && second < text.find("End").unwrap());
+        LyxDocument::parse(&text).unwrap();
+    }
+
+    #[test]
+    fn missing_marker() {
+        let mut document = LyxDocument::parse(DOCUMENT).unwrap();
+        let edit = LyxEdit::InsertSections {
+            layout: "Section".to_owned(),
+            titles: Box::new(["Question 1".to_owned()]),
+            marker: Some("NO SUCH MARKER".to_owned()),
+        };
+        assert!(document.apply(&edit).is_err());
+    }
+}
@@ -0,0 +1,340 @@
+use super::archive::ArchiveKind;
+use super::lyx::LyxEdit;
+use regex::{NoExpand, Regex};
+use std::{
+    fmt::{self, Display},
+    path::{Path, PathBuf},
+    process::{self, Command},
+};
+
+/// Choices made (e.g. interactively) when creating a new HW directory.
+/// The default options are suitable for non-interactive use.
+#[derive(Clone, Default)]
+pub struct NewHwOptions {
+    /// The file to use as the questions file, instead of the most recent download.
+    pub questions_file: Option<PathBuf>,
+    /// The files to use as attachments, instead of selecting them automatically.
+    pub attachments: Option<Box<[PathBuf]>>,
+}
+
+/// Everything that creating a new HW directory would do, computed without touching the filesystem.
+pub struct NewHwPlan {
+    pub(super) num: usize,
+    pub(super) hw_dir: PathBuf,
+    pub(super) questions_file: Option<QuestionsFilePlan>,
+    pub(super) attachments: Box<[AttachmentPlan]>,
+    pub(super) template_dir: Option<TemplateDirPlan>,
+    pub(super) documents: Box<[DocumentPlan]>,
+    pub(super) open_commands: Box<[OpenCommand]>,
+}
+
+pub struct QuestionsFilePlan {
+    pub(super) src: PathBuf,
+    pub(super) dest: PathBuf,
+    /// If set, `src` is an archive that is extracted (and then removed) instead of being moved to `dest`.
+    pub(super) extract: Option<ExtractPlan>,
+}
+
+pub struct ExtractPlan {
+    pub(super) kind: ArchiveKind,
+    pub(super) dir: PathBuf,
+    /// The questions file among the extracted files, which is moved to the questions file's `dest`.
+    pub(super) questions_file: PathBuf,
+}
+
+pub struct AttachmentPlan {
+    pub(super) src: PathBuf,
+    pub(super) dest: PathBuf,
+}
+
+pub struct TemplateDirPlan {
+    pub(super) src: PathBuf,
+    /// The directories to create, parents first.
+    pub(super) dirs: Box<[PathBuf]>,
+    /// The files to copy, each from its `template`.
+    pub(super) files: Box<[DocumentPlan]>,
+}
+
+pub struct DocumentPlan {
+    pub(super) dest: PathBuf,
+    pub(super) template: Option<PathBuf>,
+    /// Whether the template is rendered by the template engine.
+    pub(super) rendered: bool,
+    pub(super) replacements: Box<[ReplacementPlan]>,
+    pub(super) lyx_edits: Box<[LyxEdit]>,
+    /// The contents of the document, unless it is a plain copy of the template (or empty).
+    pub(super) contents: Option<String>,
+}
+
+pub struct ReplacementPlan {
+    pub(super) from: String,
+    pub(super) regex: Regex,
+    pub(super) to: String,
+    /// Whether `to` may refer to capture groups of `regex`.
+    pub(super) expand: bool,
+    pub(super) count: Option<usize>,
+    /// Number of appearances of `from` in the template.
+    pub(super) matches: usize,
+}
+
+/// Everything that exporting the LyX file of a HW would do.
+pub struct ExportPlan {
+    pub(super) lyx_file: PathBuf,
+    pub(super) output: PathBuf,
+    pub(super) command: OpenCommand,
+}
+
+/// Everything that packaging a HW for submission would do.
+pub struct PackagePlan {
+    pub(super) hw_dir: PathBuf,
+    pub(super) output: PathBuf,
+    /// The included files, relative to `hw_dir`.
+    pub(super) files: Box<[PathBuf]>,
+    pub(super) zip: bool,
+}
+
+/// Everything that opening a HW directory would do.
+pub struct OpenPlan {
+    pub(super) commands: Box<[OpenCommand]>,
+}
+
+/// An external program to spawn, with its arguments already formatted.
+pub struct OpenCommand {
+    pub(super) binary: String,
+    pub(super) args: Box<[String]>,
+}
+
+impl NewHwPlan {
+    pub fn num(&self) -> usize {
+        self.num
+    }
+}
+
+impl ExportPlan {
+    pub fn output(&self) -> &Path {
+        &self.output
+    }
+}
+
+impl PackagePlan {
+    pub fn output(&self) -> &Path {
+        &self.output
+    }
+}
+
+impl ReplacementPlan {
+    /// Number of appearances of `from` that will actually be replaced.
+    pub fn replaced(&self) -> usize {
+        self.count
+            .map_or(self.matches, |count| count.min(self.matches))
+    }
+
+    pub fn apply(&self, data: &str) -> String {
+        let count = match self.count {
+            Some(0) => return data.to_owned(),
+            Some(count) => count,
+            None => 0, // `replacen` replaces all appearances with 0
+        };
+        if self.expand {
+            self.regex.replacen(data, count, &self.to).into_owned()
+        } else {
+            self.regex
+                .replacen(data, count, NoExpand(&self.to))
+                .into_owned()
+        }
+    }
+}
+
+impl OpenCommand {
+    pub fn spawn(&self) -> anyhow::Result<()> {
+        Command::new(&self.binary)
+            .args(self.args.iter())
+            .stdin(process::Stdio::null())
+            .stdout(process::Stdio::null())
+            .stderr(process::Stdio::null())
+            .spawn()
+            .map(|_child| ()) // ignore child process
+            .map_err(Into::into)
+    }
+
+    /// Run the program and wait for it, failing (with its error output) if it fails.
+    pub fn run(&self) -> anyhow::Result<()> {
+        let output = Command::new(&self.binary)
+            .args(self.args.iter())
+            .stdin(process::Stdio::null())
+            .output()
+            .map_err(|err| anyhow::anyhow!("run {}: {err}", self.binary))?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "{} failed ({}):\n{}",
+                self.binary,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim_end()
+            );
+        }
+        Ok(())
+    }
+}
+
+impl Display for NewHwPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "HW number: {}", self.num)?;
+        writeln!(f, "Create directory: {}", self.hw_dir.display())?;
+
+        if let Some(questions_file) = &self.questions_file {
+            match &questions_file.extract {
+                Some(extract) => {
+                    writeln!(
+                        f,
+                        "Extract questions archive: {} -> {}",
+                        questions_file.src.display(),
+                        extract.dir.display(),
+                    )?;
+                    writeln!(
+                        f,
+                        "  Move questions file: {} -> {}",
+                        extract.questions_file.display(),
+                        questions_file.dest.display(),
+                    )?;
+                    writeln!(f, "  Remove archive: {}", questions_file.src.display())?;
+                }
+                None => writeln!(
+                    f,
+                    "Move questions file: {} -> {}",
+                    questions_file.src.display(),
+                    questions_file.dest.display(),
+                )?,
+            }
+        }
+
+        for attachment in self.attachments.iter() {
+            writeln!(
+                f,
+                "Move attachment: {} -> {}",
+                attachment.src.display(),
+                attachment.dest.display(),
+            )?;
+        }
+
+        if let Some(template_dir) = &self.template_dir {
+            writeln!(
+                f,
+                "Copy template directory: {} -> {}",
+                template_dir.src.display(),
+                self.hw_dir.display(),
+            )?;
+            for dir in template_dir.dirs.iter() {
+                writeln!(f, "  Create directory: {}", dir.display())?;
+            }
+            for file in template_dir.files.iter() {
+                if let Some(template) = &file.template {
+                    writeln!(
+                        f,
+                        "  Copy file: {} -> {}",
+                        template.display(),
+                        file.dest.display(),
+                    )?;
+                }
+                write_replacements(f, &file.replacements, "    ")?;
+            }
+        }
+
+        for document in self.documents.iter() {
+            match &document.template {
+                Some(template) if document.rendered => writeln!(
+                    f,
+                    "Create document: {} (rendered from template {})",
+                    document.dest.display(),
+                    template.display(),
+                )?,
+                Some(template) => writeln!(
+                    f,
+                    "Create document: {} (from template {})",
+                    document.dest.display(),
+                    template.display(),
+                )?,
+                None => writeln!(f, "Create empty document: {}", document.dest.display())?,
+            }
+            write_replacements(f, &document.replacements, "  ")?;
+            for edit in document.lyx_edits.iter() {
+                writeln!(f, "  {edit}")?;
+            }
+        }
+
+        for command in self.open_commands.iter() {
+            writeln!(f, "Run: {command}")?;
+        }
+
+        Ok(())
+    }
+}
+
+fn write_replacements(
+    f: &mut fmt::Formatter<'_>,
+    replacements: &[ReplacementPlan],
+    indent: &str,
+) -> fmt::Result {
+    for replacement in replacements {
+        writeln!(
+            f,
+            "{indent}Replace {:?} with {:?}: {} matches, {} replaced",
+            replacement.from,
+            replacement.to,
+            replacement.matches,
+            replacement.replaced(),
+        )?;
+    }
+    Ok(())
+}
+
+impl Display for ExportPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Export: {} -> {}",
+            self.lyx_file.display(),
+            self.output.display()
+        )?;
+        writeln!(f, "Run: {}", self.command)
+    }
+}
+
+impl Display for PackagePlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.zip {
+            writeln!(f, "Create zip file: {}", self.output.display())?;
+            for file in self.files.iter() {
+                writeln!(f, "  Add: {}", file.display())?;
+            }
+        } else {
+            for file in self.files.iter() {
+                writeln!(
+                    f,
+                    "Copy: {} -> {}",
+                    self.hw_dir.join(file).display(),
+                    self.output.display()
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for OpenPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for command in self.commands.iter() {
+            writeln!(f, "Run: {command}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for OpenCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.binary)?;
+        for arg in self.args.iter() {
+            write!(f, " {arg:?}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,155 @@
+use super::files::{self, update_subject_settings};
+use super::plan::PackagePlan;
+use crate::settings::Settings;
+use std::{fs, io, path::Path};
+
+/// Compute what [`package`] would do, without touching the filesystem.
+pub fn plan_package(
+    settings: &Settings,
+    subject_dir: &Path,
+    num: usize,
+) -> anyhow::Result<PackagePlan> {
+    let settings = update_subject_settings(settings, subject_dir)?;
+    let Some(submission_settings) = settings.submission_settings() else {
+        anyhow::bail!("packaging is not configured (see `submission` in the settings)");
+    };
+    let hw_dir = files::existing_hw_dir(&settings, subject_dir, num)?;
+    let output = submission_settings
+        .outbox_dir()
+        .unwrap_or(&hw_dir)
+        .join(submission_settings.filename(num, &settings.format_context())?);
+
+    let mut included = Vec::new();
+    for relative_path in files::walk_dir(&hw_dir)? {
+        let path = hw_dir.join(&relative_path);
+        // never include a previous submission
+        if path.is_file() && path != output && submission_settings.includes(&relative_path)? {
+            included.push(relative_path);
+        }
+    }
+    if included.is_empty() {
+        anyhow::bail!(
+            "no files in {} match `submission.include`",
+            hw_dir.display()
+        );
+    }
+    if !submission_settings.zip() && included.len() > 1 {
+        anyhow::bail!(
+            "{} files match `submission.include`, but only one can be submitted without `zip`",
+            included.len()
+        );
+    }
+
+    Ok(PackagePlan {
+        hw_dir,
+        output,
+        files: included.into(),
+        zip: submission_settings.zip(),
+    })
+}
+
+/// Package a HW for submission, according to a plan computed by [`plan_package`].
+/// A previous submission is overwritten.
+pub fn package(plan: &PackagePlan) -> anyhow::Result<()> {
+    if let Some(outbox_dir) = plan.output.parent() {
+        fs::create_dir_all(outbox_dir)?;
+    }
+
+    if !plan.zip {
+        fs::copy(plan.hw_dir.join(&plan.files[0]), &plan.output)?;
+        return Ok(());
+    }
+
+    let mut zip = zip::ZipWriter::new(fs::File::create(&plan.output)?);
+    for relative_path in plan.files.iter() {
+        let name = relative_path
+            .to_str()
+            .ok_or_else(|| anyhow::anyhow!("invalid filename: {}", relative_path.display()))?
+            .replace(std::path::MAIN_SEPARATOR, "/");
+        zip.start_file(name, zip::write::SimpleFileOptions::default())?;
+        io::copy(
+            &mut fs::File::open(plan.hw_dir.join(relative_path))?,
+            &mut zip,
+        )?;
+    }
+    zip.finish()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
+    use std::path::PathBuf;
+
+    fn hw_dir(name: &str, submission: &str) -> (TempDir, Settings) {
+        let dir = TempDir::new(name);
+        let settings = dir.settings(&format!(r#", "submission": {submission}"#), 2);
+        dir.write("subjects/A/HW3/HW3.lyx", "");
+        dir.write("subjects/A/HW3/HW3.pdf", "");
+        dir.write("subjects/A/HW3/figures/plot.png", "");
+        dir.write("subjects/A/HW3/figures/plot.svg", "");
+        dir.write("subjects/A/HW3/notes.md", "");
+        (dir, settings)
+    }
+
+    #[test]
+    fn include() {
+        let (dir, settings) = hw_dir(
+            "package_include",
+            r#"{ "filename_format": "hw{num}.zip", "include": ["*.lyx", "*.pdf", "figures/*.png"], "zip": true }"#,
+        );
+        // a previous submission is never included
+        dir.write("subjects/A/HW3/hw3.zip", "");
+
+        let plan = plan_package(&settings, &dir.path().join("subjects/A"), 3).unwrap();
+        assert_eq!(plan.output(), dir.path().join("subjects/A/HW3/hw3.zip"));
+        assert_eq!(
+            &*plan.files,
+            ["HW3.lyx", "HW3.pdf", "figures/plot.png"].map(PathBuf::from)
+        );
+
+        package(&plan).unwrap();
+        let zip = zip::ZipArchive::new(fs::File::open(plan.output()).unwrap()).unwrap();
+        let mut names: Vec<_> = zip.file_names().collect();
+        names.sort_unstable();
+        assert_eq!(names, ["HW3.lyx", "HW3.pdf", "figures/plot.png"]);
+    }
+
+    #[test]
+    fn single_file() {
+        let outbox_dir = TempDir::new("package_outbox");
+        let (dir, settings) = hw_dir(
+            "package_single_file",
+            &format!(
+                r#"{{ "filename_format": "hw{{num}}_123.pdf", "include": ["*.pdf"], "outbox_dir": {:?} }}"#,
+                outbox_dir.path()
+            ),
+        );
+
+        let plan = plan_package(&settings, &dir.path().join("subjects/A"), 3).unwrap();
+        assert_eq!(plan.output(), outbox_dir.path().join("hw3_123.pdf"));
+        package(&plan).unwrap();
+        assert!(outbox_dir.path().join("hw3_123.pdf").is_file());
+    }
+
+    #[test]
+    fn bad_include() {
+        let (dir, settings) = hw_dir(
+            "package_bad_include",
+            r#"{ "filename_format": "hw{num}.pdf", "include": ["figures/*"] }"#,
+        );
+        let subject_dir = dir.path().join("subjects/A");
+        let err = plan_package(&settings, &subject_dir, 3).err().unwrap();
+        assert!(err.to_string().starts_with("2 files match"), "{err}");
+
+        let (dir, settings) = hw_dir(
+            "package_no_include",
+            r#"{ "filename_format": "hw{num}.zip", "include": ["*.docx"], "zip": true }"#,
+        );
+        let subject_dir = dir.path().join("subjects/A");
+        let err = plan_package(&settings, &subject_dir, 3).err().unwrap();
+        assert!(err.to_string().starts_with("no files in"), "{err}");
+    }
+}
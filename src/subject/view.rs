@@ -0,0 +1,95 @@
+use super::{files, Subject};
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// A machine-readable description of a subject, meant to be serialized (e.g. to JSON).
+#[derive(Serialize)]
+pub struct SubjectView {
+    name: String,
+    path: PathBuf,
+    current_hw_num: usize,
+    /// HW numbers below `current_hw_num` that have no HW directory.
+    missing_hw_nums: Box<[usize]>,
+    settings: SettingsSummary,
+    hws: Box<[HwView]>,
+}
+
+/// The effective settings of a subject that are interesting to external tools.
+#[derive(Serialize)]
+struct SettingsSummary {
+    /// The per-subject settings file, if the subject has one.
+    settings_file: Option<PathBuf>,
+    hebrew_name: String,
+    hw_dir_format: String,
+    open_after_creation: bool,
+    downloads_dir: Option<PathBuf>,
+    /// The template files of the documents created in every new HW directory.
+    document_templates: Box<[PathBuf]>,
+}
+
+/// A machine-readable description of a HW directory.
+#[derive(Serialize)]
+pub struct HwView {
+    num: usize,
+    path: PathBuf,
+    files: Box<[String]>,
+}
+
+impl SubjectView {
+    pub fn new(subject: &Subject) -> anyhow::Result<Self> {
+        let settings = subject.settings();
+
+        let settings_file = settings
+            .subject_settings_filename()
+            .map(|filename| subject.path().join(filename))
+            .filter(|settings_file| settings_file.is_file());
+
+        let hws = files::list_hw_dirs(settings, subject.path())?
+            .into_vec()
+            .into_iter()
+            .map(|(num, path)| HwView::new(num, path))
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self {
+            name: subject.name().to_owned(),
+            path: subject.path().to_owned(),
+            current_hw_num: subject.current_hw_num(),
+            missing_hw_nums: files::missing_hw_nums(settings, subject.path())?,
+            settings: SettingsSummary {
+                settings_file,
+                hebrew_name: settings.hebrew_name().to_owned(),
+                hw_dir_format: settings.hw_dir_format().to_owned(),
+                open_after_creation: settings.open_after_creation(),
+                downloads_dir: settings
+                    .questions_file_settings()
+                    .map(|questions_file_settings| {
+                        questions_file_settings.downloads_dir().to_owned()
+                    }),
+                document_templates: settings
+                    .documents()
+                    .iter()
+                    .filter_map(|document_settings| document_settings.template_file())
+                    .map(ToOwned::to_owned)
+                    .collect(),
+            },
+            hws,
+        })
+    }
+}
+
+impl HwView {
+    pub fn new(num: usize, path: PathBuf) -> anyhow::Result<Self> {
+        let mut files: Vec<_> = path
+            .read_dir()?
+            .filter_map(Result::ok)
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .collect();
+        files.sort_unstable();
+
+        Ok(Self {
+            num,
+            path,
+            files: files.into(),
+        })
+    }
+}
@@ -0,0 +1,64 @@
+use crate::{clock::Clock, settings::Settings};
+use chrono::{Local, TimeZone};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// An empty directory for a test, removed when dropped.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "manage_hw_directory-{name}-{}-{}",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        if dir.exists() {
+            fs::remove_dir_all(&dir).unwrap();
+        }
+        fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Write settings with the subjects in `subjects`, along with `extra` settings (starting with a comma),
+    /// and get them with a clock fixed on the given day of November 2026.
+    pub fn settings(&self, extra: &str, day: u32) -> Settings {
+        let json = format!(
+            r#"{{
+                "subjects_dir": {:?},
+                "hw_dir_format": "HW{{num}}",
+                "open_after_creation": false,
+                "interface": {{ "type": "CMD", "subject_label_format": "{{name}}" }}
+                {extra}
+            }}"#,
+            self.0.join("subjects")
+        );
+        let settings_file = self.write("settings.json", &json);
+        let now = Local.with_ymd_and_hms(2026, 11, day, 12, 0, 0).unwrap();
+        Settings::new(&settings_file.display().to_string())
+            .unwrap()
+            .with_clock(Clock::Fixed(now))
+    }
+
+    /// Write a file (and its missing parent directories) under the directory, returning its path.
+    pub fn write(&self, relative_path: &str, contents: &str) -> PathBuf {
+        let path = self.0.join(relative_path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}